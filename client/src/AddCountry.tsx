@@ -2,17 +2,20 @@ import React, { useState, useEffect } from 'react';
 import './AddCountry.css';
 import { FontAwesomeIcon } from '@fortawesome/react-fontawesome';
 import { faInfoCircle } from '@fortawesome/free-solid-svg-icons';
+import { CountrySummary } from './types';
 
 interface CountryFormProps {
   onAddCountry: (country: string) => void;
   onRemoveCountry: (country: string) => void;
   countries: string[];
+  availableCountries: CountrySummary[];
 }
 
 const CountryForm: React.FC<CountryFormProps> = ({
   onAddCountry,
   countries: propCountries,
   onRemoveCountry,
+  availableCountries,
 }) => {
   const [selectedCountry, setSelectedCountry] = useState<string>('');
   const [countries, setCountries] = useState<string[]>(propCountries); 
@@ -60,21 +63,11 @@ const CountryForm: React.FC<CountryFormProps> = ({
         className="select"
       >
         <option value="">Select a country</option>
-        <option value="Australia">Australia</option>
-        <option value="Canada (excl. provincial taxes)">
-          Canada (excluding provincial taxes)
-        </option>
-        <option value="Ireland">Ireland</option>
-        <option value="Netherlands">Netherlands</option>
-        <option value="New Zealand">New Zealand</option>
-        <option value="Norway">Norway</option>
-        <option value="Singapore">Singapore</option>
-        <option value="South Africa">South Africa</option>
-        <option value="Spain">Spain</option>
-        <option value="United Kingdom">United Kingdom</option>
-        <option value="United States of America (excl. state taxes)">
-          United States of America (excluding state taxes)
-        </option>
+        {availableCountries.map((country) => (
+          <option key={country.id} value={country.id} title={country.metadata.notes}>
+            {country.id}
+          </option>
+        ))}
       </select>
 
       <ul className="list">
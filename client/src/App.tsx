@@ -12,7 +12,7 @@ import TaxData from './TaxTable';
 import IncomeData from './IncomeTable';
 import ExchangeRateData from './ExchangeRateTable';
 import BreakevenData from './BreakevenTable';
-import { BackEndResponse } from './types';
+import { BackEndResponse, CountrySummary } from './types';
 import { useCallback } from 'react'
 
 const BACKEND_API_URL: string = `${window._env_.REACT_APP_BACKEND_PROTOCOL}://${window._env_.REACT_APP_BACKEND_HOST}`;
@@ -25,6 +25,7 @@ const App: React.FC = () => {
   const [incomeError, setIncomeError] = useState<string | null>(null);
   const [maxIncomeError, setMaxIncomeError] = useState<string | null>(null);
   const [countries, setCountries] = useState<string[]>([]);
+  const [availableCountries, setAvailableCountries] = useState<CountrySummary[]>([]);
   const [responseData, setresponseData] = useState<BackEndResponse | null>(null);
   const plotElementRef = useRef<HTMLDivElement | null>(null);
   const [globalOptions, setGlobalOptions] = useState<GlobalOptions>({
@@ -134,7 +135,7 @@ const App: React.FC = () => {
       return
     }
 
-    const backendEndpoint: string = `${hostname}/process`;
+    const backendEndpoint: string = `${hostname}/v1/process`;
 
     try {
       setLoading(true);
@@ -151,6 +152,12 @@ const App: React.FC = () => {
   }, [countries, currency, globalOptions]);
 
   // Effects
+  useEffect(() => {
+    axios.get<CountrySummary[]>(`${BACKEND_API_URL}/v1/countries`)
+      .then((response) => setAvailableCountries(response.data))
+      .catch(() => alert(`Could not load countries from ${BACKEND_API_URL}/v1/countries`));
+  }, []);
+
   useEffect(() => {
     if (responseData && plotElementRef.current) {
       plotElementRef.current?.scrollIntoView({behavior: 'smooth'});
@@ -176,6 +183,7 @@ const App: React.FC = () => {
         onAddCountry={handleAddCountry}
         onRemoveCountry={handleRemoveCountry}
         countries={countries}
+        availableCountries={availableCountries}
       />
       <CurrencyForm
         currency={currency}
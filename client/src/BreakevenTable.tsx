@@ -25,18 +25,20 @@ const BreakevenData: React.FC<BreakevenProps> = ({ data, currency }) => {
     return null
   }
 
-  const tableRows = Object.entries(data.country_comb_data).map(
-    ([country_comb, breakevenData]) => {
+  const tableRows = data.country_comb_data.map(
+    (breakevenData, pairIndex) => {
       const incomes: number[] = breakevenData.breakeven_incomes;
       const rates: number[] = breakevenData.breakeven_effective_tax_rates;
       const amounts: number[] = breakevenData.breakeven_tax_amounts;
       return incomes.map((income, index) => (
-        <tr key={`${country_comb}-${index}`}>
-          <td>{country_comb.split('-')[0]}</td>
-          <td>{country_comb.split('-')[1]}</td>
+        <tr key={`${pairIndex}-${index}`}>
+          <td>{breakevenData.country_a}</td>
+          <td>{breakevenData.country_b}</td>
           <td>{income.toFixed(0)}</td>
           <td>{(100.0*rates[index]).toFixed(1)}</td>
           <td>{amounts[index].toFixed(0)}</td>
+          <td>{breakevenData.cheaper_below[index] ?? "Neither"}</td>
+          <td>{breakevenData.cheaper_above[index] ?? "Neither"}</td>
         </tr>
       ));
     });
@@ -58,7 +60,7 @@ const BreakevenData: React.FC<BreakevenProps> = ({ data, currency }) => {
           <table ref={tableRef} className="breakeven-table">
             <thead>
               <tr>
-                <th colSpan={5}>Breakeven Points:</th>
+                <th colSpan={7}>Breakeven Points:</th>
               </tr>
               <tr>
                 <th>Country 1</th>
@@ -66,12 +68,14 @@ const BreakevenData: React.FC<BreakevenProps> = ({ data, currency }) => {
                 <th>Income where taxes are equal between countries ({currency})</th>
                 <th>Taxation %</th>
                 <th>Taxation Amount ({currency})</th>
+                <th>Cheaper below</th>
+                <th>Cheaper above</th>
               </tr>
             </thead>
             <tbody>
-              {isEmptyArray(tableRows) || (Object.keys(data.country_comb_data).length === 0) ? (
+              {isEmptyArray(tableRows) || (data.country_comb_data.length === 0) ? (
                 <tr>
-                  <td colSpan={5}>No breakeven points found for countries</td>
+                  <td colSpan={7}>No breakeven points found for countries</td>
                 </tr>
               ) : (
                 tableRows
@@ -44,7 +44,7 @@ const IncomeData: React.FC<IncomeDataProps> = ({ data, income, currency }) => {
       {/*Never will be null if income is null, but need these checks to keep TS happy*/}
       <td>{entry.taxInfo.specific_tax_rate?.toFixed(2)}</td>
       <td>{entry.taxInfo.specific_tax_amount?.toFixed(0)}</td>
-      <td>{(income - (entry.taxInfo.specific_tax_amount || 0.0)).toFixed(0)}</td>
+      <td>{entry.taxInfo.specific_net_income?.toFixed(0)}</td>
     </tr>
   ));
 
@@ -24,7 +24,7 @@ const TaxAmountsPlot: React.FC<TaxAmountsPlotProps> = ({ data }) => {
   const shapes: Partial<Plotly.Shape>[] = [];
   const scatterData: Plotly.Data[] = [];
   if (data.country_comb_data) {
-    Object.entries(data.country_comb_data).flatMap(([country_comb_key, pointsData]) => {
+    data.country_comb_data.forEach((pointsData) => {
       const incomes = pointsData.breakeven_incomes;
       const rates = pointsData.breakeven_tax_amounts;
       incomes.forEach((income, index) => {
@@ -64,7 +64,7 @@ const TaxAmountsPlot: React.FC<TaxAmountsPlotProps> = ({ data }) => {
             color: 'black',
             size: 4,
           },
-          name: `${country_comb_key} Breakeven (#${index + 1})`,
+          name: `${pointsData.country_a} vs ${pointsData.country_b} Breakeven (#${index + 1})`,
           showlegend: false,
           });
       });
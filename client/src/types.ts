@@ -4,23 +4,61 @@ export interface TaxBracket {
   income_limit: number;
 };
 
+export interface CountrySummary {
+  id: string;
+  currency: string;
+  bracket_count: number;
+  top_marginal_rate: number;
+  metadata: {
+    tax_year?: string;
+    source?: string;
+    notes?: string;
+  };
+};
+
 export interface BackEndResponse {
 
   country_comb_data: {
-    [country_comb_key: string]: {
-      breakeven_incomes: number[];
-      breakeven_tax_amounts: number[];
-      breakeven_effective_tax_rates: number[];
-    };
-  } | null;
+    country_a: string;
+    country_b: string;
+    breakeven_incomes: number[];
+    breakeven_tax_amounts: number[];
+    breakeven_effective_tax_rates: number[];
+    cheaper_below: (string | null)[];
+    cheaper_above: (string | null)[];
+  }[] | null;
 
   country_specific_data: {
     [country_key: string]: {
       effective_tax_rates: number[];
       incomes: number[];
+      tax_amount_knots?: { income_limit: number; income_tax_amount: number }[];
+      effective_rate_segments?: {
+        income_start: number;
+        income_end: number;
+        slope: number;
+        intercept: number;
+      }[];
       specific_tax_amount: number | null;
       specific_tax_rate: number | null;
+      specific_net_income: number | null;
+      specific_marginal_rate: number | null;
+      specific_bracket: number | null;
+      specific_bracket_taxes?: {
+        income_start: number;
+        income_end: number | null;
+        marginal_rate: number;
+        income_taxed: number;
+        tax_amount: number;
+      }[];
+      pay_periods?: {
+        period: 'annual' | 'monthly' | 'fortnightly' | 'weekly';
+        gross_income: number;
+        tax_amount: number;
+        net_income: number;
+      }[];
       tax_amounts: number[];
+      net_incomes?: number[];
       tax_brackets: TaxBracket[];
       exchange_rate: number | null;
       currency: string | null;
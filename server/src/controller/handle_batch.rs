@@ -0,0 +1,315 @@
+use crate::controller::schedule_cache::ScheduleCache;
+use crate::controller::taxes_config::TaxesConfig;
+use crate::errors::{ErrorBody, TaxError};
+use crate::exchange_rates::historical::HistoricalExchangeRates;
+use crate::exchange_rates::ppp::PppConversionFactors;
+use crate::exchange_rates::ExchangeRateProvider;
+use actix_web::web::Bytes;
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse};
+use futures_util::stream;
+use log::info;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Largest batch body accepted, in bytes.
+pub const MAX_BATCH_BYTES: usize = 16 * 1024 * 1024;
+/// Rows serialised per streamed chunk of the response.
+const ROWS_PER_CHUNK: usize = 1000;
+
+/// One income to evaluate.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BatchRow {
+    pub id: String,
+    pub country: String,
+    pub income: f32,
+    /// Currency the income is given in, the country's own currency when empty.
+    #[serde(default)]
+    pub currency: Option<String>,
+}
+
+/// Result of one row. Rows that cannot be evaluated have an `error` and no amounts.
+#[derive(Debug, PartialEq, Serialize, ToSchema)]
+pub struct BatchResultRow {
+    pub id: String,
+    pub country: String,
+    pub income: f32,
+    /// Currency of `income`, `tax_amount` and `net_income`, empty for unknown countries.
+    pub currency: Option<String>,
+    pub tax_amount: Option<f32>,
+    pub effective_tax_rate: Option<f32>,
+    pub net_income: Option<f32>,
+    pub error: Option<String>,
+}
+
+/// Read rows from a `text/csv` body with an `id,country,income,currency` header,
+/// or from an `application/json` array of row objects.
+pub fn parse_batch_rows(content_type: &str, body: &[u8]) -> Result<Vec<BatchRow>, TaxError> {
+    let rows: Vec<BatchRow> = match content_type {
+        "text/csv" => csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_reader(body)
+            .deserialize()
+            .collect::<Result<_, csv::Error>>()
+            .map_err(|err| TaxError::invalid_request("body", err.to_string()))?,
+        "application/json" => serde_json::from_slice(body)
+            .map_err(|err| TaxError::invalid_request("body", err.to_string()))?,
+        other => {
+            return Err(TaxError::invalid_request(
+                "body",
+                format!("expected text/csv or application/json, got {:?}", other),
+            ))
+        }
+    };
+    Ok(rows
+        .into_iter()
+        .map(|row| BatchRow {
+            currency: row.currency.filter(|currency| !currency.is_empty()),
+            ..row
+        })
+        .collect())
+}
+
+impl TaxesConfig {
+    /// Convert each country's schedule once per currency, for the rows that can be evaluated.
+    pub async fn prepare_batch(
+        &self,
+        rows: &[BatchRow],
+        exchange_rate_provider: &dyn ExchangeRateProvider,
+        historical_exchange_rates: &HistoricalExchangeRates,
+        ppp_conversion_factors: &PppConversionFactors,
+    ) -> ScheduleCache {
+        self.schedule_cache(
+            rows.iter()
+                .filter(|row| self.validate_batch_row(row).is_ok())
+                .map(|row| (row.country.as_str(), &row.currency, row.income)),
+            exchange_rate_provider,
+            historical_exchange_rates,
+            ppp_conversion_factors,
+        )
+        .await
+    }
+
+    fn validate_batch_row(&self, row: &BatchRow) -> Result<(), TaxError> {
+        self.country_schedule(&row.country)?;
+        if !(row.income.is_finite() && row.income >= 0.0) {
+            return Err(TaxError::invalid_request(
+                "income",
+                format!("must not be negative, got {}", row.income),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Evaluate one row with the schedules from `prepare_batch`, reporting any problem in
+    /// the row's `error`.
+    pub fn evaluate_batch_row(&self, cache: &ScheduleCache, row: &BatchRow) -> BatchResultRow {
+        let income_tax = self.validate_batch_row(row).and_then(|()| {
+            cache
+                .try_get(&row.country, &row.currency)?
+                .income_tax(row.income)
+        });
+        let (income_tax, error) = match income_tax {
+            Ok(income_tax) => (Some(income_tax), None),
+            Err(err) => (None, Some(err.to_string())),
+        };
+        BatchResultRow {
+            id: row.id.clone(),
+            country: row.country.clone(),
+            income: row.income,
+            currency: row
+                .currency
+                .clone()
+                .or_else(|| self.get_currency(&row.country).map(str::to_string)),
+            tax_amount: income_tax.as_ref().map(|income_tax| income_tax.tax_amount),
+            effective_tax_rate: income_tax
+                .as_ref()
+                .map(|income_tax| income_tax.effective_tax_rate),
+            net_income: income_tax.as_ref().map(|income_tax| income_tax.net_income),
+            error,
+        }
+    }
+}
+
+fn csv_chunk(rows: &[BatchResultRow], with_header: bool) -> Result<Bytes, actix_web::Error> {
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(with_header)
+        .from_writer(Vec::new());
+    for row in rows {
+        writer
+            .serialize(row)
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+    }
+    let bytes = writer
+        .into_inner()
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(Bytes::from(bytes))
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/batch",
+    request_body(
+        description = "Rows with an `id,country,income,currency` header, or a JSON array of rows",
+        content(
+            (String = "text/csv"),
+            (Vec<BatchRow> = "application/json"),
+        )
+    ),
+    responses(
+        (
+            status = 200,
+            description = "One `BatchResultRow` per input row, evaluated and streamed as CSV in chunks",
+            content_type = "text/csv",
+            body = String,
+            headers(("X-Exchange-Rates-Stale" = bool, description = "Set when any rates used were stale"))
+        ),
+        (status = 400, description = "Malformed request", body = ErrorBody),
+        (status = 404, description = "Unknown country or currency", body = ErrorBody),
+        (status = 422, description = "Request cannot be processed", body = ErrorBody),
+        (status = 502, description = "Exchange rates unavailable", body = ErrorBody),
+    )
+)]
+pub async fn handle_batch(
+    req: HttpRequest,
+    body: Bytes,
+    config: web::Data<TaxesConfig>,
+    exchange_rate_provider: web::Data<dyn ExchangeRateProvider>,
+    historical_exchange_rates: web::Data<HistoricalExchangeRates>,
+    ppp_conversion_factors: web::Data<PppConversionFactors>,
+) -> Result<HttpResponse, TaxError> {
+    let rows = parse_batch_rows(req.content_type(), &body)?;
+    info!("Received batch of {} rows", rows.len());
+    let cache = config
+        .prepare_batch(
+            &rows,
+            exchange_rate_provider.get_ref(),
+            &historical_exchange_rates,
+            &ppp_conversion_factors,
+        )
+        .await;
+    let exchange_rates_stale = cache.exchange_rates_stale;
+    // Rows are evaluated a chunk at a time as the response is sent.
+    let chunk_count = rows.len().div_ceil(ROWS_PER_CHUNK).max(1);
+    let chunks = stream::iter((0..chunk_count).map(move |i| {
+        let end = rows.len().min((i + 1) * ROWS_PER_CHUNK);
+        let results: Vec<BatchResultRow> = rows[i * ROWS_PER_CHUNK..end]
+            .par_iter()
+            .map(|row| config.evaluate_batch_row(&cache, row))
+            .collect();
+        csv_chunk(&results, i == 0)
+    }));
+    Ok(HttpResponse::Ok()
+        .content_type("text/csv")
+        .insert_header(("X-Exchange-Rates-Stale", exchange_rates_stale.to_string()))
+        .streaming(chunks))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::controller::handle_batch::{csv_chunk, parse_batch_rows, BatchResultRow};
+    use crate::controller::test_fixtures::Fixture;
+    use crate::errors::TaxError;
+    use crate::exchange_rates::static_provider::StaticExchangeRateProvider;
+
+    #[test]
+    fn test_parse_batch_rows() {
+        let csv = "id,country,income,currency\n1,Foo,100000,\n2, Foo ,50000,USD\n";
+        let rows = parse_batch_rows("text/csv", csv.as_bytes()).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].currency, None);
+        assert_eq!(rows[1].country, "Foo");
+        assert_eq!(rows[1].currency, Some("USD".to_string()));
+
+        let json = r#"[{"id": "1", "country": "Foo", "income": 100000}]"#;
+        let rows = parse_batch_rows("application/json", json.as_bytes()).unwrap();
+        assert_eq!(rows[0].income, 100000.0);
+
+        assert!(parse_batch_rows("text/plain", b"").is_err());
+        assert!(parse_batch_rows("text/csv", b"id,country,income\n1,Foo,lots\n").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_process_batch() {
+        let provider =
+            StaticExchangeRateProvider::from_file("test_data/exchange_rates.csv").unwrap();
+        let fixture = Fixture::new("test_data/foo.json").with_provider(provider.clone());
+        let (results, stale) = fixture
+            .batch("id,country,income,currency\na,Foo,250000,\nb,Foo,0,\nc,Foo,100000,\n")
+            .await;
+        assert!(!stale);
+        assert_eq!(
+            results[0],
+            BatchResultRow {
+                id: "a".to_string(),
+                country: "Foo".to_string(),
+                income: 250000.0,
+                currency: Some("XTS".to_string()),
+                tax_amount: Some(45000.0),
+                effective_tax_rate: Some(0.18),
+                net_income: Some(205000.0),
+                error: None,
+            }
+        );
+        assert_eq!(results[1].tax_amount, Some(0.0));
+        assert_eq!(results[2].tax_amount, Some(10000.0));
+
+        // Incomes in another currency go through that currency's rates.
+        let nz_au_fixture = Fixture::new("test_data/valid_config.json").with_provider(provider);
+        let (results, _) = nz_au_fixture
+            .batch("id,country,income,currency\na,Australia,100000,\nb,Australia,100000,NZD\n")
+            .await;
+        assert_eq!(results[1].currency, Some("NZD".to_string()));
+        // 100000 NZD is 90000 AUD, which is taxed at 0.9 times the AUD amount.
+        let australia = nz_au_fixture
+            .taxes_config
+            .get_country("Australia")
+            .unwrap()
+            .to_income_amount_schedule(100000.0)
+            .compute_specific_income_tax(Some(90000.0))
+            .unwrap();
+        assert!((results[1].tax_amount.unwrap() - australia / 0.9).abs() < 1e-2);
+        assert!(results[1].tax_amount < results[0].tax_amount);
+
+        // Bad rows get an error each, without failing the others.
+        let (results, _) = fixture
+            .batch(
+            "id,country,income,currency\na,Bar,1,\nb,Foo,-1,\nc,Foo,100000,ZZZ\nd,Foo,100000,\n",
+        )
+        .await;
+        let errors: Vec<Option<String>> = results.iter().map(|row| row.error.clone()).collect();
+        assert_eq!(
+            errors,
+            vec![
+                Some(TaxError::UnknownCountry("Bar".to_string()).to_string()),
+                Some(
+                    TaxError::invalid_request("income", "must not be negative, got -1").to_string()
+                ),
+                Some(TaxError::UnknownCurrency("ZZZ".to_string()).to_string()),
+                None,
+            ]
+        );
+        assert_eq!(results[0].currency, None);
+        assert_eq!(results[1].tax_amount, None);
+        assert_eq!(results[3].tax_amount, Some(10000.0));
+    }
+
+    #[test]
+    fn test_csv_chunk_has_error_column() {
+        let row = BatchResultRow {
+            id: "a".to_string(),
+            country: "Bar".to_string(),
+            income: 1.0,
+            currency: None,
+            tax_amount: None,
+            effective_tax_rate: None,
+            net_income: None,
+            error: Some("Unknown country Bar".to_string()),
+        };
+        assert_eq!(
+            csv_chunk(&[row], true).unwrap(),
+            "id,country,income,currency,tax_amount,effective_tax_rate,net_income,error\n\
+             a,Bar,1.0,,,,,Unknown country Bar\n"
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use crate::controller::taxes_config::{CountryMetadata, TaxesConfig};
+use crate::core::schedules::amount_schedule::IncomeTaxAmountSchedule;
+use crate::core::schedules::marginal_schedule::MarginalIncomeTaxRateSchedule;
+use crate::errors::{ErrorBody, TaxError};
+use actix_web::web;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+/// Smallest income the derived tax amount knots are extended to by default.
+const MIN_DEFAULT_MAX_INCOME: f32 = 100000.0;
+
+#[derive(Serialize, ToSchema)]
+pub struct CountrySummary {
+    pub id: String,
+    pub currency: String,
+    pub bracket_count: usize,
+    /// Marginal rate of the highest bracket.
+    pub top_marginal_rate: f32,
+    pub metadata: CountryMetadata,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CountryDetail {
+    pub id: String,
+    pub currency: String,
+    pub metadata: CountryMetadata,
+    pub marginal_rate_schedule: MarginalIncomeTaxRateSchedule,
+    /// Income the tax amount knots are extended to, in the country's currency.
+    pub max_income: f32,
+    pub tax_amount_schedule: IncomeTaxAmountSchedule,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct CountryDetailQuery {
+    /// Extend the tax amount knots to this income.
+    /// Defaults to twice the highest threshold, and at least 100000.
+    pub max_income: Option<f32>,
+}
+
+impl TaxesConfig {
+    /// Summaries of every configured country, sorted by id.
+    pub fn country_summaries(&self) -> Vec<CountrySummary> {
+        let mut summaries: Vec<CountrySummary> = self
+            .country_map
+            .iter()
+            .map(|(id, country_taxes)| {
+                let schedule = country_taxes.tax_schedule.schedule();
+                CountrySummary {
+                    id: id.clone(),
+                    currency: country_taxes.currency.clone(),
+                    bracket_count: schedule.len(),
+                    top_marginal_rate: schedule
+                        .last()
+                        .map(|knot| knot.marginal_rate())
+                        .unwrap_or(0.0),
+                    metadata: country_taxes.metadata.clone(),
+                }
+            })
+            .collect();
+        summaries.sort_by(|a, b| a.id.cmp(&b.id));
+        summaries
+    }
+
+    /// Full schedule of a country, with its tax amount knots up to `max_income`.
+    pub fn country_detail(
+        &self,
+        id: &str,
+        max_income: Option<f32>,
+    ) -> Result<CountryDetail, TaxError> {
+        let country_taxes = self
+            .country_map
+            .get(id)
+            .ok_or_else(|| TaxError::UnknownCountry(id.to_string()))?;
+        let schedule = &country_taxes.tax_schedule;
+        let max_income = match max_income {
+            Some(max_income) if !(max_income.is_finite() && max_income > 0.0) => {
+                return Err(TaxError::invalid_request(
+                    "max_income",
+                    format!("must be positive, got {}", max_income),
+                ))
+            }
+            Some(max_income) => max_income,
+            None => schedule
+                .schedule()
+                .iter()
+                .filter_map(|knot| knot.income_limit())
+                .filter(|limit| limit.is_finite())
+                .fold(MIN_DEFAULT_MAX_INCOME, |max_income, limit| {
+                    max_income.max(2.0 * limit)
+                }),
+        };
+        Ok(CountryDetail {
+            id: id.to_string(),
+            currency: country_taxes.currency.clone(),
+            metadata: country_taxes.metadata.clone(),
+            marginal_rate_schedule: schedule.clone(),
+            max_income,
+            tax_amount_schedule: schedule.to_income_amount_schedule(max_income),
+        })
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/countries",
+    responses((status = 200, description = "Every configured country", body = Vec<CountrySummary>))
+)]
+pub async fn handle_countries(config: web::Data<TaxesConfig>) -> web::Json<Vec<CountrySummary>> {
+    web::Json(config.country_summaries())
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/countries/{id}",
+    params(("id" = String, Path, description = "Country id"), CountryDetailQuery),
+    responses(
+        (status = 200, description = "Full schedule of the country", body = CountryDetail),
+        (status = 400, description = "Invalid `max_income`", body = ErrorBody),
+        (status = 404, description = "Unknown country", body = ErrorBody),
+    )
+)]
+pub async fn handle_country(
+    id: web::Path<String>,
+    query: web::Query<CountryDetailQuery>,
+    config: web::Data<TaxesConfig>,
+) -> Result<web::Json<CountryDetail>, TaxError> {
+    Ok(web::Json(config.country_detail(&id, query.max_income)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::controller::taxes_config::TaxesConfig;
+    use crate::core::points::tax_amount::IncomeTaxKnot;
+    use crate::errors::TaxError;
+
+    #[test]
+    fn test_country_summaries() {
+        let taxes_config = TaxesConfig::load("test_data/valid_config.json").unwrap();
+        let summaries = taxes_config.country_summaries();
+        let ids: Vec<&str> = summaries
+            .iter()
+            .map(|summary| summary.id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["Australia", "New Zealand"]);
+        assert_eq!(summaries[1].currency, "NZD");
+        assert_eq!(summaries[1].bracket_count, 5);
+        assert_eq!(summaries[1].top_marginal_rate, 0.39);
+    }
+
+    #[test]
+    fn test_country_detail() {
+        let taxes_config = TaxesConfig::load("test_data/foo.json").unwrap();
+        let detail = taxes_config.country_detail("Foo", Some(390000.0)).unwrap();
+        assert_eq!(detail.currency, "XTS");
+        assert_eq!(
+            detail.tax_amount_schedule.schedule().last(),
+            Some(&IncomeTaxKnot::new(390000.0, 96000.0))
+        );
+        // Highest threshold is 300000, so knots go to twice that by default.
+        let detail = taxes_config.country_detail("Foo", None).unwrap();
+        assert_eq!(detail.max_income, 600000.0);
+
+        assert_eq!(
+            taxes_config.country_detail("Bar", None).err(),
+            Some(TaxError::UnknownCountry("Bar".to_string()))
+        );
+        assert!(taxes_config.country_detail("Foo", Some(-1.0)).is_err());
+    }
+}
@@ -0,0 +1,183 @@
+use crate::controller::handle_request::ExchangeRateOptions;
+use crate::controller::handle_tax::IncomeTax;
+use crate::controller::schedule_cache::CachedSchedule;
+use crate::controller::taxes_config::{
+    EffectiveExchangeRate, ResolvedExchangeRates, TaxesConfig, MAX_GROSS_INCOME,
+};
+use crate::errors::{ErrorBody, TaxError};
+use crate::exchange_rates::historical::HistoricalExchangeRates;
+use crate::exchange_rates::ppp::PppConversionFactors;
+use crate::exchange_rates::ExchangeRateProvider;
+use actix_web::web;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Exactly one of `net_income` and `effective_tax_rate` must be given.
+#[derive(Serialize, Debug, Deserialize, ToSchema)]
+pub struct GrossIncomeRequest {
+    pub country: String,
+    /// Take-home income in the normalizing currency, or the country's own currency without one.
+    pub net_income: Option<f32>,
+    pub effective_tax_rate: Option<f32>,
+    #[serde(flatten)]
+    pub exchange_rate_options: ExchangeRateOptions,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct GrossIncomeResponse {
+    pub country: String,
+    pub currency: Option<String>,
+    pub exchange_rate: EffectiveExchangeRate,
+    pub exchange_rates_stale: bool,
+    /// Taxes on the lowest gross income meeting the target.
+    pub tax: IncomeTax,
+}
+
+impl TaxesConfig {
+    /// Find the lowest gross income with a given net income or effective tax rate.
+    pub async fn process_gross_income_request(
+        &self,
+        req: &GrossIncomeRequest,
+        exchange_rate_provider: &dyn ExchangeRateProvider,
+        historical_exchange_rates: &HistoricalExchangeRates,
+        ppp_conversion_factors: &PppConversionFactors,
+    ) -> Result<GrossIncomeResponse, TaxError> {
+        let schedule = self.country_schedule(&req.country)?;
+        match (req.net_income, req.effective_tax_rate) {
+            (Some(net_income), None) if !(net_income.is_finite() && net_income >= 0.0) => {
+                return Err(TaxError::invalid_request(
+                    "net_income",
+                    format!("must not be negative, got {}", net_income),
+                ));
+            }
+            (None, Some(rate)) if !rate.is_finite() => {
+                return Err(TaxError::invalid_request(
+                    "effective_tax_rate",
+                    format!("must be finite, got {}", rate),
+                ));
+            }
+            (Some(_), None) | (None, Some(_)) => {}
+            _ => {
+                return Err(TaxError::invalid_request(
+                    "net_income",
+                    "exactly one of net_income and effective_tax_rate must be given",
+                ));
+            }
+        }
+        let countries = [req.country.clone()];
+        let ResolvedExchangeRates {
+            currency,
+            mut exchange_rates,
+            exchange_rates_stale,
+            ..
+        } = self
+            .effective_exchange_rates(
+                &countries,
+                &req.exchange_rate_options,
+                exchange_rate_provider,
+                historical_exchange_rates,
+                ppp_conversion_factors,
+            )
+            .await?;
+        let schedule = CachedSchedule::new(
+            schedule,
+            exchange_rates
+                .swap_remove(&req.country)
+                .ok_or_else(|| TaxError::UnknownCountry(req.country.clone()))?,
+            MAX_GROSS_INCOME,
+        );
+        let gross_income = match (req.net_income, req.effective_tax_rate) {
+            (Some(net_income), _) => schedule.amount_schedule().gross_for_net(net_income)?,
+            (_, Some(rate)) => schedule.amount_schedule().income_for_effective_rate(rate)?,
+            (None, None) => unreachable!("validated above"),
+        };
+        Ok(GrossIncomeResponse {
+            country: req.country.clone(),
+            currency,
+            tax: schedule.income_tax(gross_income)?,
+            exchange_rate: schedule.exchange_rate,
+            exchange_rates_stale,
+        })
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/gross_income",
+    request_body = GrossIncomeRequest,
+    responses(
+        (status = 200, description = "Gross income meeting the target", body = GrossIncomeResponse),
+        (status = 400, description = "Malformed request", body = ErrorBody),
+        (status = 404, description = "Unknown country or currency", body = ErrorBody),
+        (status = 422, description = "Target out of reach or not invertible", body = ErrorBody),
+        (status = 502, description = "Exchange rates unavailable", body = ErrorBody),
+    )
+)]
+pub async fn handle_gross_income(
+    req: web::Json<GrossIncomeRequest>,
+    config: web::Data<TaxesConfig>,
+    exchange_rate_provider: web::Data<dyn ExchangeRateProvider>,
+    historical_exchange_rates: web::Data<HistoricalExchangeRates>,
+    ppp_conversion_factors: web::Data<PppConversionFactors>,
+) -> Result<web::Json<GrossIncomeResponse>, TaxError> {
+    info!("Received gross income request: {:?}", req);
+    let response = config
+        .process_gross_income_request(
+            &req.into_inner(),
+            exchange_rate_provider.get_ref(),
+            &historical_exchange_rates,
+            &ppp_conversion_factors,
+        )
+        .await
+        .inspect_err(|e| warn!("Error processing gross income request: {}", e))?;
+    Ok(web::Json(response))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::controller::test_fixtures::Fixture;
+    use crate::errors::TaxError;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_process_gross_income_request() {
+        let fixture = Fixture::new("test_data/foo.json");
+
+        let response = fixture
+            .gross_income(json!({"country": "Foo", "net_income": 205000.0}))
+            .await
+            .unwrap();
+        assert_eq!(response.tax.income, 250000.0);
+        assert_eq!(response.tax.net_income, 205000.0);
+
+        let response = fixture
+            .gross_income(json!({"country": "Foo", "effective_tax_rate": 0.18}))
+            .await
+            .unwrap();
+        assert!((response.tax.income - 250000.0).abs() < 1.0);
+
+        // The bottom bracket taxes every income in it at 10%.
+        assert!(matches!(
+            fixture
+                .gross_income(json!({"country": "Foo", "effective_tax_rate": 0.1}))
+                .await,
+            Err(TaxError::NonInvertible { .. })
+        ));
+        // The effective rate never reaches the top marginal rate.
+        assert!(matches!(
+            fixture
+                .gross_income(json!({"country": "Foo", "effective_tax_rate": 0.4}))
+                .await,
+            Err(TaxError::TargetOutOfBounds { .. })
+        ));
+        assert!(matches!(
+            fixture
+                .gross_income(
+                    json!({"country": "Foo", "net_income": 1.0, "effective_tax_rate": 0.2})
+                )
+                .await,
+            Err(TaxError::InvalidRequest { .. })
+        ));
+    }
+}
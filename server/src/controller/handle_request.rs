@@ -1,39 +1,234 @@
-use crate::controller::taxes_config::{BreakevenData, TaxData, TaxesConfig};
-use actix_web::{web, HttpResponse, Responder};
-use log::info;
+use crate::controller::taxes_config::{BreakevenData, EffectiveExchangeRate, TaxData, TaxesConfig};
+use crate::controller::uncertainty::ExchangeRateUncertainty;
+use crate::errors::{ErrorBody, TaxError};
+use crate::exchange_rates::historical::HistoricalExchangeRates;
+use crate::exchange_rates::ppp::PppConversionFactors;
+use crate::exchange_rates::ExchangeRateProvider;
+use actix_web::error::JsonPayloadError;
+use actix_web::{web, HttpRequest};
+use chrono::NaiveDate;
+use indexmap::IndexMap;
+use log::{info, warn};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct TaxPlotDataResponse {
-    pub country_specific_data: HashMap<String, TaxData>,
-    pub country_comb_data: Option<HashMap<String, BreakevenData>>,
+    /// Keyed by country, in request order.
+    pub country_specific_data: IndexMap<String, TaxData>,
+    /// One entry per pair of requested countries, in request order.
+    pub country_comb_data: Option<Vec<BreakevenData>>,
+    /// Set when the exchange rates used are past their update time and could not be refreshed.
+    pub exchange_rates_stale: bool,
+    /// Date of the historical exchange rates used, which is the nearest earlier date
+    /// with rates when none are available on the requested `exchange_rate_date`.
+    pub exchange_rate_date: Option<NaiveDate>,
+    /// The exchange rate applied to each country, and where it came from.
+    pub exchange_rates: IndexMap<String, EffectiveExchangeRate>,
 }
 
-#[derive(Serialize, Debug, Deserialize)]
+#[derive(Serialize, Debug, Deserialize, ToSchema)]
 pub struct TaxPlotDataRequest {
     pub countries: Vec<String>,
     pub income: Option<f32>,
     pub max_income: f32,
     pub show_break_even: bool,
+    #[serde(flatten)]
+    pub exchange_rate_options: ExchangeRateOptions,
+    /// Return sampled arrays or the schedule's knots.
+    #[serde(default)]
+    pub representation: Representation,
+    /// Largest error in the effective rate curve drawn through the sampled incomes,
+    /// 0.0001 (0.01 percentage points) by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_effective_rate_error: Option<f32>,
+    /// Lowest income of the dense curves, 0 by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_income: Option<f32>,
+    /// Evenly spaced incomes instead of adaptive sampling.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grid: Option<GridSpacing>,
+    /// Spacing of the `grid`: in units of currency for `linear` (default 10),
+    /// in decades for `log` (default 0.01).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub step: Option<f32>,
+    /// Evaluate the dense curves at exactly these incomes, returned sorted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub incomes: Option<Vec<f32>>,
+    /// Sample exchange rate scenarios and return percentile bands.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exchange_rate_uncertainty: Option<ExchangeRateUncertainty>,
+}
+
+/// How amounts in different currencies are brought into a common currency.
+#[derive(Clone, Serialize, Debug, Default, Deserialize, ToSchema)]
+pub struct ExchangeRateOptions {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub normalizing_currency: Option<String>,
+    /// Normalize with market exchange rates, or with purchasing power parity factors.
+    #[serde(default)]
+    pub normalization: Normalization,
+    /// Normalize with the rates as of this date (`YYYY-MM-DD`) instead of the latest rates.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exchange_rate_date: Option<NaiveDate>,
+    /// Override rates by currency, in units of the currency per unit of the normalizing currency.
+    /// These take precedence over fetched rates.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exchange_rates: Option<BTreeMap<String, f32>>,
+    /// Override rates by country, taking precedence over `exchange_rates`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub country_exchange_rates: Option<BTreeMap<String, f32>>,
+}
+
+/// How tax curves are returned.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Representation {
+    /// `incomes`, `tax_amounts` and `effective_tax_rates` sampled from 0 to `max_income`,
+    /// densely near knots and sparsely where the effective rate has converged.
+    #[default]
+    Dense,
+    /// `tax_amount_knots` and `effective_rate_segments`, which describe the curves exactly.
+    Knots,
+}
+
+/// Spacing of an evenly spaced income grid.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum GridSpacing {
+    /// `min_income`, `min_income + step`, ... up to `max_income`.
+    Linear,
+    /// Incomes a constant ratio `10^step` apart, from a positive `min_income`.
+    Log,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Normalization {
+    #[default]
+    Market,
+    /// Amounts are expressed in the PPP factors' base currency, so curves compare
+    /// purchasing power rather than market value.
+    Ppp,
 }
 
+#[utoipa::path(
+    post,
+    path = "/v1/process",
+    request_body = TaxPlotDataRequest,
+    responses(
+        (status = 200, description = "Tax curves and breakevens", body = TaxPlotDataResponse),
+        (status = 400, description = "Malformed request", body = ErrorBody),
+        (status = 404, description = "Unknown country or currency", body = ErrorBody),
+        (status = 422, description = "Request cannot be processed", body = ErrorBody),
+        (status = 502, description = "Exchange rates unavailable", body = ErrorBody),
+    )
+)]
 pub async fn handle_request(
     req: web::Json<TaxPlotDataRequest>,
     config: web::Data<TaxesConfig>,
-) -> impl Responder {
+    exchange_rate_provider: web::Data<dyn ExchangeRateProvider>,
+    historical_exchange_rates: web::Data<HistoricalExchangeRates>,
+    ppp_conversion_factors: web::Data<PppConversionFactors>,
+) -> Result<web::Json<TaxPlotDataResponse>, TaxError> {
     info!("Received request: {:?}", req);
-    let res = match &config.process_request(&req.into_inner()).await {
-        Ok(response) => {
-            info!("Processed request successfully");
-            HttpResponse::Ok().json(response)
-        }
-        Err(e) => {
-            eprint!("Error processing request: {:?}", e);
-            HttpResponse::InternalServerError().finish()
-        }
-    };
-    return res;
+    let response = config
+        .process_request(
+            &req.into_inner(),
+            exchange_rate_provider.get_ref(),
+            &historical_exchange_rates,
+            &ppp_conversion_factors,
+        )
+        .await
+        .inspect_err(|e| warn!("Error processing request: {}", e))?;
+    info!("Processed request successfully");
+    Ok(web::Json(response))
+}
+
+/// Report malformed JSON bodies in the same shape as other request errors.
+pub fn json_error_handler(err: JsonPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    TaxError::invalid_request("body", err.to_string()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::controller::handle_request::{handle_request, json_error_handler};
+    use crate::controller::taxes_config::TaxesConfig;
+    use crate::exchange_rates::historical::HistoricalExchangeRates;
+    use crate::exchange_rates::ppp::PppConversionFactors;
+    use crate::exchange_rates::static_provider::StaticExchangeRateProvider;
+    use crate::exchange_rates::ExchangeRateProvider;
+    use actix_web::http::StatusCode;
+    use actix_web::{test, web, App};
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+
+    #[actix_web::test]
+    async fn test_handle_request_errors_are_json() {
+        let provider: Arc<dyn ExchangeRateProvider> = Arc::new(
+            StaticExchangeRateProvider::from_file("test_data/exchange_rates.json").unwrap(),
+        );
+        let app = test::init_service(
+            App::new()
+                .app_data(web::JsonConfig::default().error_handler(json_error_handler))
+                .app_data(web::Data::new(
+                    TaxesConfig::load("test_data/valid_config.json").unwrap(),
+                ))
+                .app_data(web::Data::from(provider))
+                .app_data(web::Data::new(HistoricalExchangeRates::default()))
+                .app_data(web::Data::new(PppConversionFactors::default()))
+                .route("/process", web::post().to(handle_request)),
+        )
+        .await;
+        let post = |body: Value| {
+            test::TestRequest::post()
+                .uri("/process")
+                .set_json(body)
+                .to_request()
+        };
+        let request = |extra: Value| {
+            let mut body = json!({
+                "countries": ["New Zealand", "Australia"],
+                "max_income": 100000.0,
+                "show_break_even": false,
+                "normalizing_currency": "NZD"
+            });
+            body.as_object_mut()
+                .unwrap()
+                .extend(extra.as_object().unwrap().clone());
+            post(body)
+        };
+
+        let resp = test::call_service(&app, request(json!({}))).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = test::call_service(&app, request(json!({"countries": ["Atlantis"]}))).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["error"], "unknown_country");
+        assert_eq!(body["country"], "Atlantis");
+
+        let resp = test::call_service(&app, request(json!({"normalizing_currency": "XXX"}))).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["currency"], "XXX");
+
+        let resp = test::call_service(&app, request(json!({"income": -5.0}))).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["field"], "income");
+
+        let resp = test::call_service(
+            &app,
+            request(json!({"normalization": "ppp", "normalizing_currency": "NZD"})),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let resp = test::call_service(&app, post(json!({"countries": "New Zealand"}))).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["field"], "body");
+    }
 }
@@ -0,0 +1,228 @@
+use crate::controller::handle_request::ExchangeRateOptions;
+use crate::controller::handle_tax::IncomeTax;
+use crate::controller::schedule_cache::ScheduleCache;
+use crate::controller::taxes_config::{EffectiveExchangeRate, TaxesConfig, MAX_GROSS_INCOME};
+use crate::errors::{ErrorBody, TaxError};
+use crate::exchange_rates::historical::HistoricalExchangeRates;
+use crate::exchange_rates::ppp::PppConversionFactors;
+use crate::exchange_rates::ExchangeRateProvider;
+use actix_web::web;
+use indexmap::IndexMap;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Serialize, Debug, Deserialize, ToSchema)]
+pub struct SalaryEquivalenceRequest {
+    pub country: String,
+    /// Gross income in `country`, in the normalizing currency.
+    pub income: f32,
+    /// Countries to find the gross income leaving the same net income in.
+    pub target_countries: Vec<String>,
+    #[serde(flatten)]
+    pub exchange_rate_options: ExchangeRateOptions,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SalaryEquivalenceResponse {
+    pub country: String,
+    pub currency: Option<String>,
+    pub exchange_rates_stale: bool,
+    /// The exchange rate applied to each country, and where it came from.
+    pub exchange_rates: IndexMap<String, EffectiveExchangeRate>,
+    /// Taxes on `income` in `country`.
+    pub tax: IncomeTax,
+    /// Taxes on the lowest gross income leaving the same net income, keyed by target country
+    /// in request order.
+    pub equivalents: IndexMap<String, IncomeTax>,
+}
+
+impl TaxesConfig {
+    /// Find the gross income each target country must pay to leave the same net income as
+    /// `income` does in the source country. Each country's schedule is converted once.
+    pub async fn process_salary_equivalence_request(
+        &self,
+        req: &SalaryEquivalenceRequest,
+        exchange_rate_provider: &dyn ExchangeRateProvider,
+        historical_exchange_rates: &HistoricalExchangeRates,
+        ppp_conversion_factors: &PppConversionFactors,
+    ) -> Result<SalaryEquivalenceResponse, TaxError> {
+        if !(req.income.is_finite() && (0.0..=MAX_GROSS_INCOME).contains(&req.income)) {
+            return Err(TaxError::invalid_request(
+                "income",
+                format!(
+                    "must be between 0 and {}, got {}",
+                    MAX_GROSS_INCOME, req.income
+                ),
+            ));
+        }
+        if req.target_countries.is_empty() {
+            return Err(TaxError::invalid_request(
+                "target_countries",
+                "must not be empty",
+            ));
+        }
+        // Targets' gross incomes are not known up front, so their schedules run to
+        // MAX_GROSS_INCOME. That only adds a final knot, so costs nothing over a tighter bound.
+        let mut max_incomes: IndexMap<&str, f32> = IndexMap::new();
+        max_incomes.insert(&req.country, req.income);
+        for country in &req.target_countries {
+            max_incomes.insert(country, MAX_GROSS_INCOME);
+        }
+        let mut cache = ScheduleCache::default();
+        let currency = self
+            .cache_schedules(
+                &mut cache,
+                max_incomes
+                    .iter()
+                    .map(|(country, income)| (*country, *income)),
+                &req.exchange_rate_options,
+                exchange_rate_provider,
+                historical_exchange_rates,
+                ppp_conversion_factors,
+            )
+            .await?;
+        if currency.is_none()
+            && max_incomes
+                .keys()
+                .any(|country| self.get_currency(country) != self.get_currency(&req.country))
+        {
+            return Err(TaxError::unprocessable_request(
+                "normalizing_currency",
+                "required to compare countries with different currencies",
+            ));
+        }
+        let schedule = |country: &str| {
+            cache
+                .get(country, &req.exchange_rate_options.normalizing_currency)
+                .ok_or_else(|| TaxError::UnknownCountry(country.to_string()))
+        };
+        let tax = schedule(&req.country)?.income_tax(req.income)?;
+        let equivalents = req
+            .target_countries
+            .iter()
+            .map(|country| {
+                let schedule = schedule(country)?;
+                let gross_income = schedule.amount_schedule().gross_for_net(tax.net_income)?;
+                Ok((country.clone(), schedule.income_tax(gross_income)?))
+            })
+            .collect::<Result<IndexMap<String, IncomeTax>, TaxError>>()?;
+        let exchange_rates = max_incomes
+            .keys()
+            .map(|country| {
+                Ok((
+                    country.to_string(),
+                    schedule(country)?.exchange_rate.clone(),
+                ))
+            })
+            .collect::<Result<IndexMap<String, EffectiveExchangeRate>, TaxError>>()?;
+        Ok(SalaryEquivalenceResponse {
+            country: req.country.clone(),
+            currency,
+            exchange_rates_stale: cache.exchange_rates_stale,
+            exchange_rates,
+            tax,
+            equivalents,
+        })
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/salary_equivalence",
+    request_body = SalaryEquivalenceRequest,
+    responses(
+        (status = 200, description = "Equivalent gross income in each target country", body = SalaryEquivalenceResponse),
+        (status = 400, description = "Malformed request", body = ErrorBody),
+        (status = 404, description = "Unknown country or currency", body = ErrorBody),
+        (status = 422, description = "Net income out of reach or not invertible", body = ErrorBody),
+        (status = 502, description = "Exchange rates unavailable", body = ErrorBody),
+    )
+)]
+pub async fn handle_salary_equivalence(
+    req: web::Json<SalaryEquivalenceRequest>,
+    config: web::Data<TaxesConfig>,
+    exchange_rate_provider: web::Data<dyn ExchangeRateProvider>,
+    historical_exchange_rates: web::Data<HistoricalExchangeRates>,
+    ppp_conversion_factors: web::Data<PppConversionFactors>,
+) -> Result<web::Json<SalaryEquivalenceResponse>, TaxError> {
+    info!("Received salary equivalence request: {:?}", req);
+    let response = config
+        .process_salary_equivalence_request(
+            &req.into_inner(),
+            exchange_rate_provider.get_ref(),
+            &historical_exchange_rates,
+            &ppp_conversion_factors,
+        )
+        .await
+        .inspect_err(|e| warn!("Error processing salary equivalence request: {}", e))?;
+    Ok(web::Json(response))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::controller::test_fixtures::Fixture;
+    use crate::errors::TaxError;
+    use crate::utils::adjust_exchange_rate_schedule;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_process_salary_equivalence_request() {
+        let fixture = Fixture::new("test_data/valid_config.json");
+        let response = fixture
+            .salary_equivalence(json!({
+                "country": "New Zealand",
+                "income": 80000.0,
+                "target_countries": ["Australia", "New Zealand"],
+                "normalizing_currency": "NZD"
+            }))
+            .await
+            .unwrap();
+        assert_eq!(
+            response.equivalents.keys().collect::<Vec<_>>(),
+            vec!["Australia", "New Zealand"]
+        );
+        assert_eq!(response.equivalents["New Zealand"], response.tax);
+        // Australia's gross income leaves the same net income once converted.
+        let australia = &response.equivalents["Australia"];
+        assert!((australia.net_income - response.tax.net_income).abs() < 1e-1);
+        let net_income = australia.income
+            - adjust_exchange_rate_schedule(
+                &fixture.taxes_config,
+                "Australia",
+                &Some(0.9),
+                200000.0,
+            )
+            .unwrap()
+            .compute_income_taxes(&[australia.income])
+            .unwrap()[0];
+        assert!((net_income - response.tax.net_income).abs() < 1e-1);
+
+        // Incomes on a bracket threshold map back onto the threshold.
+        let uk_fixture = Fixture::new("assets/taxes.json");
+        for income in [12570.0, 50270.0, 125140.0] {
+            let response = uk_fixture
+                .salary_equivalence(json!({
+                    "country": "United Kingdom",
+                    "income": income,
+                    "target_countries": ["United Kingdom"]
+                }))
+                .await
+                .unwrap();
+            assert_eq!(response.equivalents["United Kingdom"], response.tax);
+            assert_eq!(response.tax.income, income);
+        }
+
+        // Incomes in different currencies cannot be compared without normalizing them.
+        assert!(matches!(
+            uk_fixture
+                .salary_equivalence(json!({
+                    "country": "New Zealand",
+                    "income": 80000.0,
+                    "target_countries": ["Australia"]
+                }))
+                .await,
+            Err(TaxError::UnprocessableRequest { .. })
+        ));
+    }
+}
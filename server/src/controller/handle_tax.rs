@@ -0,0 +1,204 @@
+use crate::controller::handle_request::ExchangeRateOptions;
+use crate::controller::schedule_cache::CachedSchedule;
+use crate::controller::taxes_config::{EffectiveExchangeRate, ResolvedExchangeRates, TaxesConfig};
+use crate::errors::{ErrorBody, TaxError};
+use crate::exchange_rates::historical::HistoricalExchangeRates;
+use crate::exchange_rates::ppp::PppConversionFactors;
+use crate::exchange_rates::ExchangeRateProvider;
+use actix_web::web;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Serialize, Debug, Deserialize, ToSchema)]
+pub struct TaxRequest {
+    pub country: String,
+    /// Incomes in the normalizing currency, or the country's own currency without one.
+    pub incomes: Vec<f32>,
+    #[serde(flatten)]
+    pub exchange_rate_options: ExchangeRateOptions,
+}
+
+/// Taxes on a single income.
+#[derive(Debug, PartialEq, Serialize, ToSchema)]
+pub struct IncomeTax {
+    pub income: f32,
+    pub tax_amount: f32,
+    pub effective_tax_rate: f32,
+    /// Rate paid on the next unit of income.
+    pub marginal_rate: f32,
+    pub net_income: f32,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TaxResponse {
+    pub country: String,
+    pub currency: Option<String>,
+    pub exchange_rate: EffectiveExchangeRate,
+    pub exchange_rates_stale: bool,
+    /// One entry per requested income, in the same order.
+    pub taxes: Vec<IncomeTax>,
+}
+
+impl TaxesConfig {
+    /// Taxes on a handful of incomes in one country, without generating a range.
+    pub async fn process_tax_request(
+        &self,
+        req: &TaxRequest,
+        exchange_rate_provider: &dyn ExchangeRateProvider,
+        historical_exchange_rates: &HistoricalExchangeRates,
+        ppp_conversion_factors: &PppConversionFactors,
+    ) -> Result<TaxResponse, TaxError> {
+        let schedule = self.country_schedule(&req.country)?;
+        if req.incomes.is_empty() {
+            return Err(TaxError::invalid_request("incomes", "must not be empty"));
+        }
+        if let Some(income) = req
+            .incomes
+            .iter()
+            .find(|income| !(income.is_finite() && **income >= 0.0))
+        {
+            return Err(TaxError::invalid_request(
+                "incomes",
+                format!("must not be negative, got {}", income),
+            ));
+        }
+        let countries = [req.country.clone()];
+        let ResolvedExchangeRates {
+            currency,
+            mut exchange_rates,
+            exchange_rates_stale,
+            ..
+        } = self
+            .effective_exchange_rates(
+                &countries,
+                &req.exchange_rate_options,
+                exchange_rate_provider,
+                historical_exchange_rates,
+                ppp_conversion_factors,
+            )
+            .await?;
+        let max_income = req.incomes.iter().copied().fold(0.0, f32::max);
+        let schedule = CachedSchedule::new(
+            schedule,
+            exchange_rates
+                .swap_remove(&req.country)
+                .ok_or_else(|| TaxError::UnknownCountry(req.country.clone()))?,
+            max_income,
+        );
+        let taxes = req
+            .incomes
+            .iter()
+            .map(|&income| schedule.income_tax(income))
+            .collect::<Result<Vec<IncomeTax>, TaxError>>()?;
+        Ok(TaxResponse {
+            country: req.country.clone(),
+            currency,
+            exchange_rate: schedule.exchange_rate,
+            exchange_rates_stale,
+            taxes,
+        })
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/tax",
+    request_body = TaxRequest,
+    responses(
+        (status = 200, description = "Taxes on each income", body = TaxResponse),
+        (status = 400, description = "Malformed request", body = ErrorBody),
+        (status = 404, description = "Unknown country or currency", body = ErrorBody),
+        (status = 422, description = "Request cannot be processed", body = ErrorBody),
+        (status = 502, description = "Exchange rates unavailable", body = ErrorBody),
+    )
+)]
+pub async fn handle_tax(
+    req: web::Json<TaxRequest>,
+    config: web::Data<TaxesConfig>,
+    exchange_rate_provider: web::Data<dyn ExchangeRateProvider>,
+    historical_exchange_rates: web::Data<HistoricalExchangeRates>,
+    ppp_conversion_factors: web::Data<PppConversionFactors>,
+) -> Result<web::Json<TaxResponse>, TaxError> {
+    info!("Received tax request: {:?}", req);
+    let response = config
+        .process_tax_request(
+            &req.into_inner(),
+            exchange_rate_provider.get_ref(),
+            &historical_exchange_rates,
+            &ppp_conversion_factors,
+        )
+        .await
+        .inspect_err(|e| warn!("Error processing tax request: {}", e))?;
+    Ok(web::Json(response))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::controller::handle_tax::IncomeTax;
+    use crate::controller::taxes_config::ExchangeRateSource;
+    use crate::controller::test_fixtures::Fixture;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_process_tax_request() {
+        let fixture = Fixture::new("test_data/foo.json");
+        let response = fixture
+            .tax(json!({
+                "country": "Foo",
+                "incomes": [250000.0, 0.0, 100000.0]
+            }))
+            .await
+            .unwrap();
+        assert_eq!(response.currency, None);
+        assert_eq!(
+            response.exchange_rate.source,
+            ExchangeRateSource::Unadjusted
+        );
+        assert_eq!(
+            response.taxes,
+            vec![
+                IncomeTax {
+                    income: 250000.0,
+                    tax_amount: 45000.0,
+                    effective_tax_rate: 0.18,
+                    marginal_rate: 0.3,
+                    net_income: 205000.0,
+                },
+                IncomeTax {
+                    income: 0.0,
+                    tax_amount: 0.0,
+                    effective_tax_rate: 0.0,
+                    marginal_rate: 0.1,
+                    net_income: 0.0,
+                },
+                IncomeTax {
+                    income: 100000.0,
+                    tax_amount: 10000.0,
+                    effective_tax_rate: 0.1,
+                    marginal_rate: 0.2,
+                    net_income: 90000.0,
+                },
+            ]
+        );
+
+        // Thresholds move with the exchange rate: 2 XTS per unit puts 100000 in the top bracket.
+        let response = fixture
+            .tax(json!({
+                "country": "Foo",
+                "incomes": [100000.0],
+                "normalizing_currency": "USD",
+                "exchange_rates": {"XTS": 2.0}
+            }))
+            .await
+            .unwrap();
+        assert_eq!(response.currency, Some("USD".to_string()));
+        assert_eq!(response.taxes[0].tax_amount, 15000.0);
+        assert_eq!(response.taxes[0].marginal_rate, 0.3);
+
+        assert!(fixture
+            .tax(json!({"country": "Foo", "incomes": [-1.0]}))
+            .await
+            .is_err());
+    }
+}
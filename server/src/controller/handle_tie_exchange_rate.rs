@@ -0,0 +1,181 @@
+use crate::controller::handle_request::ExchangeRateOptions;
+use crate::controller::taxes_config::{EffectiveExchangeRate, ResolvedExchangeRates, TaxesConfig};
+use crate::core::exchange_rate_tie::solve_tie_exchange_rate;
+use crate::errors::{ErrorBody, TaxError};
+use crate::exchange_rates::historical::HistoricalExchangeRates;
+use crate::exchange_rates::ppp::PppConversionFactors;
+use crate::exchange_rates::ExchangeRateProvider;
+use actix_web::web;
+use indexmap::IndexMap;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Serialize, Debug, Deserialize, ToSchema)]
+pub struct TieExchangeRateRequest {
+    pub country_a: String,
+    pub country_b: String,
+    /// Income in the normalizing currency.
+    pub income: f32,
+    #[serde(flatten)]
+    pub exchange_rate_options: ExchangeRateOptions,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TieExchangeRateResponse {
+    /// Units of country B's currency per unit of country A's currency today.
+    pub current_exchange_rate: f32,
+    /// Units of country B's currency per unit of country A's currency at which both countries
+    /// tax `income` the same, and so leave the same net income.
+    pub tie_exchange_rate: f32,
+    /// How far B's currency would have to move against A's for the countries to tie,
+    /// e.g. -0.1 when B's currency would need to strengthen by 10%.
+    pub relative_distance: f32,
+    /// Tax amount of both countries at the tie, in the normalizing currency.
+    pub tie_tax_amount: f32,
+    pub currency: Option<String>,
+    pub exchange_rates_stale: bool,
+    /// The exchange rate applied to each country today, and where it came from.
+    pub exchange_rates: IndexMap<String, EffectiveExchangeRate>,
+}
+
+impl TaxesConfig {
+    /// Find the exchange rate at which two countries tax an income the same.
+    /// Country A's rate is held fixed while country B's is solved for.
+    pub async fn process_tie_exchange_rate_request(
+        &self,
+        req: &TieExchangeRateRequest,
+        exchange_rate_provider: &dyn ExchangeRateProvider,
+        historical_exchange_rates: &HistoricalExchangeRates,
+        ppp_conversion_factors: &PppConversionFactors,
+    ) -> Result<TieExchangeRateResponse, TaxError> {
+        if !(req.income.is_finite() && req.income > 0.0) {
+            return Err(TaxError::invalid_request(
+                "income",
+                format!("must be positive, got {}", req.income),
+            ));
+        }
+        let countries = [req.country_a.clone(), req.country_b.clone()];
+        let ResolvedExchangeRates {
+            currency,
+            exchange_rates,
+            exchange_rates_stale,
+            ..
+        } = self
+            .effective_exchange_rates(
+                &countries,
+                &req.exchange_rate_options,
+                exchange_rate_provider,
+                historical_exchange_rates,
+                ppp_conversion_factors,
+            )
+            .await?;
+        let rate_a = exchange_rates
+            .get(&req.country_a)
+            .ok_or_else(|| TaxError::UnknownCountry(req.country_a.clone()))?
+            .rate;
+        let rate_b = exchange_rates
+            .get(&req.country_b)
+            .ok_or_else(|| TaxError::UnknownCountry(req.country_b.clone()))?
+            .rate;
+        let schedule_a = self.country_schedule(&req.country_a)?;
+        let tie_rate_b = solve_tie_exchange_rate(
+            schedule_a,
+            rate_a,
+            self.country_schedule(&req.country_b)?,
+            rate_b,
+            req.income,
+        )?;
+        let tie_tax_amount = schedule_a
+            .exchange_rate_adjustment(&Some(rate_a))
+            .to_income_amount_schedule(req.income)
+            .compute_income_taxes(&[req.income])?[0];
+        Ok(TieExchangeRateResponse {
+            current_exchange_rate: rate_b / rate_a,
+            tie_exchange_rate: tie_rate_b / rate_a,
+            relative_distance: tie_rate_b / rate_b - 1.0,
+            tie_tax_amount,
+            currency,
+            exchange_rates_stale,
+            exchange_rates,
+        })
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/tie_exchange_rate",
+    request_body = TieExchangeRateRequest,
+    responses(
+        (status = 200, description = "Exchange rate at which both countries tie", body = TieExchangeRateResponse),
+        (status = 400, description = "Malformed request", body = ErrorBody),
+        (status = 404, description = "Unknown country or currency", body = ErrorBody),
+        (status = 422, description = "Request cannot be processed", body = ErrorBody),
+        (status = 502, description = "Exchange rates unavailable", body = ErrorBody),
+    )
+)]
+pub async fn handle_tie_exchange_rate(
+    req: web::Json<TieExchangeRateRequest>,
+    config: web::Data<TaxesConfig>,
+    exchange_rate_provider: web::Data<dyn ExchangeRateProvider>,
+    historical_exchange_rates: web::Data<HistoricalExchangeRates>,
+    ppp_conversion_factors: web::Data<PppConversionFactors>,
+) -> Result<web::Json<TieExchangeRateResponse>, TaxError> {
+    info!("Received tie exchange rate request: {:?}", req);
+    let response = config
+        .process_tie_exchange_rate_request(
+            &req.into_inner(),
+            exchange_rate_provider.get_ref(),
+            &historical_exchange_rates,
+            &ppp_conversion_factors,
+        )
+        .await
+        .inspect_err(|e| warn!("Error processing tie exchange rate request: {}", e))?;
+    Ok(web::Json(response))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::controller::test_fixtures::Fixture;
+    use crate::utils::adjust_exchange_rate_schedule;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_process_tie_exchange_rate_request() {
+        let fixture = Fixture::new("test_data/valid_config.json");
+        let response = fixture
+            .tie_exchange_rate(json!({
+                "country_a": "New Zealand",
+                "country_b": "Australia",
+                "income": 50000.0,
+                "normalizing_currency": "NZD"
+            }))
+            .await
+            .unwrap();
+        assert_eq!(response.current_exchange_rate, 0.9);
+        assert!(
+            (response.relative_distance - (response.tie_exchange_rate / 0.9 - 1.0)).abs() < 1e-6
+        );
+        // At the tie rate both countries take the same tax.
+        let tax_at = |country: &str, rate: f32| {
+            adjust_exchange_rate_schedule(&fixture.taxes_config, country, &Some(rate), 100000.0)
+                .unwrap()
+                .compute_income_taxes(&[50000.0])
+                .unwrap()[0]
+        };
+        let new_zealand = tax_at("New Zealand", 1.0);
+        let australia = tax_at("Australia", response.tie_exchange_rate);
+        assert!((australia / new_zealand - 1.0).abs() < 1e-5);
+        assert!((response.tie_tax_amount / new_zealand - 1.0).abs() < 1e-5);
+
+        assert!(fixture
+            .tie_exchange_rate(json!({
+                "country_a": "New Zealand",
+                "country_b": "Atlantis",
+                "income": 50000.0,
+                "normalizing_currency": "NZD"
+            }))
+            .await
+            .is_err());
+    }
+}
@@ -1,2 +1,13 @@
+pub mod handle_batch;
+pub mod handle_countries;
+pub mod handle_gross_income;
 pub mod handle_request;
+pub mod handle_salary_equivalence;
+pub mod handle_tax;
+pub mod handle_tie_exchange_rate;
+pub mod openapi;
+pub mod schedule_cache;
 pub mod taxes_config;
+#[cfg(test)]
+pub mod test_fixtures;
+pub mod uncertainty;
@@ -0,0 +1,72 @@
+use crate::controller::{
+    handle_batch, handle_countries, handle_gross_income, handle_request, handle_salary_equivalence,
+    handle_tax, handle_tie_exchange_rate,
+};
+use actix_web::web;
+use utoipa::OpenApi;
+
+/// OpenAPI 3 description of the `/v1` routes.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "taxes-compare",
+        description = "Compare income taxes across countries."
+    ),
+    paths(
+        handle_request::handle_request,
+        handle_tax::handle_tax,
+        handle_gross_income::handle_gross_income,
+        handle_salary_equivalence::handle_salary_equivalence,
+        handle_batch::handle_batch,
+        handle_countries::handle_countries,
+        handle_countries::handle_country,
+        handle_tie_exchange_rate::handle_tie_exchange_rate,
+    ),
+    components(schemas(handle_batch::BatchResultRow))
+)]
+pub struct ApiDoc;
+
+pub async fn handle_openapi() -> web::Json<utoipa::openapi::OpenApi> {
+    web::Json(ApiDoc::openapi())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::controller::openapi::ApiDoc;
+    use utoipa::OpenApi;
+
+    #[test]
+    fn test_openapi_document() {
+        let doc = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        assert!(doc["openapi"].as_str().unwrap().starts_with("3."));
+        for path in [
+            "/v1/process",
+            "/v1/tax",
+            "/v1/gross_income",
+            "/v1/salary_equivalence",
+            "/v1/batch",
+            "/v1/countries",
+            "/v1/countries/{id}",
+            "/v1/tie_exchange_rate",
+        ] {
+            assert!(doc["paths"].get(path).is_some(), "missing {}", path);
+        }
+        let schemas = &doc["components"]["schemas"];
+        for schema in [
+            "TaxPlotDataRequest",
+            "TaxData",
+            "ErrorBody",
+            "BatchResultRow",
+        ] {
+            assert!(schemas.get(schema).is_some(), "missing {}", schema);
+        }
+        // Flattened options are referenced from the request bodies.
+        assert!(schemas["TaxPlotDataRequest"]
+            .to_string()
+            .contains("#/components/schemas/ExchangeRateOptions"));
+        assert!(schemas["ExchangeRateOptions"]["properties"]
+            .get("normalizing_currency")
+            .is_some());
+        assert!(schemas["TaxData"]["properties"].get("incomes").is_some());
+    }
+}
@@ -0,0 +1,184 @@
+use crate::controller::handle_request::ExchangeRateOptions;
+use crate::controller::handle_tax::IncomeTax;
+use crate::controller::taxes_config::{EffectiveExchangeRate, ResolvedExchangeRates, TaxesConfig};
+use crate::core::schedules::amount_schedule::IncomeTaxAmountSchedule;
+use crate::core::schedules::marginal_schedule::MarginalIncomeTaxRateSchedule;
+use crate::errors::TaxError;
+use crate::exchange_rates::historical::HistoricalExchangeRates;
+use crate::exchange_rates::ppp::PppConversionFactors;
+use crate::exchange_rates::ExchangeRateProvider;
+use std::collections::{BTreeMap, HashMap};
+
+/// A country's schedule converted to one currency, ready to evaluate incomes up to `max_income`.
+pub struct CachedSchedule {
+    pub exchange_rate: EffectiveExchangeRate,
+    marginal_schedule: MarginalIncomeTaxRateSchedule,
+    amount_schedule: IncomeTaxAmountSchedule,
+    max_income: f32,
+}
+
+impl CachedSchedule {
+    pub fn new(
+        schedule: &MarginalIncomeTaxRateSchedule,
+        exchange_rate: EffectiveExchangeRate,
+        max_income: f32,
+    ) -> Self {
+        let marginal_schedule = schedule.exchange_rate_adjustment(&Some(exchange_rate.rate));
+        let amount_schedule = marginal_schedule.to_income_amount_schedule(max_income);
+        Self {
+            exchange_rate,
+            marginal_schedule,
+            amount_schedule,
+            max_income,
+        }
+    }
+
+    pub fn amount_schedule(&self) -> &IncomeTaxAmountSchedule {
+        &self.amount_schedule
+    }
+
+    /// Taxes on one income, which must be between zero and `max_income`.
+    pub fn income_tax(&self, income: f32) -> Result<IncomeTax, TaxError> {
+        let tax_amount = if income == 0.0 {
+            0.0
+        } else {
+            self.amount_schedule
+                .compute_specific_income_tax(Some(income))
+                .ok_or(TaxError::IncomeOutOfBounds {
+                    income,
+                    bounds: (0.0, self.max_income),
+                })?
+        };
+        Ok(IncomeTax {
+            income,
+            tax_amount,
+            effective_tax_rate: if income == 0.0 {
+                0.0
+            } else {
+                tax_amount / income
+            },
+            marginal_rate: self.marginal_schedule.marginal_rate_at(income)?,
+            net_income: income - tax_amount,
+        })
+    }
+}
+
+/// Schedules keyed by country and the currency incomes are given in,
+/// so each one is converted once however many incomes use it.
+#[derive(Default)]
+pub struct ScheduleCache {
+    schedules: HashMap<(String, Option<String>), CachedSchedule>,
+    /// Why no schedules could be built for a currency.
+    currency_errors: HashMap<Option<String>, TaxError>,
+    pub exchange_rates_stale: bool,
+}
+
+impl ScheduleCache {
+    pub fn get(&self, country: &str, currency: &Option<String>) -> Option<&CachedSchedule> {
+        self.schedules.get(&(country.to_string(), currency.clone()))
+    }
+
+    /// The schedule for `country` in `currency`, or why there is none.
+    pub fn try_get(
+        &self,
+        country: &str,
+        currency: &Option<String>,
+    ) -> Result<&CachedSchedule, TaxError> {
+        self.get(country, currency).ok_or_else(|| {
+            self.currency_errors
+                .get(currency)
+                .cloned()
+                .unwrap_or_else(|| TaxError::UnknownCountry(country.to_string()))
+        })
+    }
+}
+
+impl TaxesConfig {
+    /// Build a schedule for every `(country, currency, income)` to be evaluated.
+    /// A `None` currency leaves incomes in the country's own currency. A currency whose
+    /// schedules cannot be built is recorded in the cache rather than failing every currency.
+    pub async fn schedule_cache<'a>(
+        &self,
+        incomes: impl IntoIterator<Item = (&'a str, &'a Option<String>, f32)>,
+        exchange_rate_provider: &dyn ExchangeRateProvider,
+        historical_exchange_rates: &HistoricalExchangeRates,
+        ppp_conversion_factors: &PppConversionFactors,
+    ) -> ScheduleCache {
+        // Highest income per country for each currency.
+        let mut max_incomes: BTreeMap<&Option<String>, BTreeMap<&str, f32>> = BTreeMap::new();
+        for (country, currency, income) in incomes {
+            let max_income = max_incomes
+                .entry(currency)
+                .or_default()
+                .entry(country)
+                .or_insert(0.0);
+            *max_income = max_income.max(income);
+        }
+        let mut cache = ScheduleCache::default();
+        for (currency, countries) in max_incomes {
+            if let Err(err) = self
+                .cache_schedules(
+                    &mut cache,
+                    countries,
+                    &ExchangeRateOptions {
+                        normalizing_currency: currency.clone(),
+                        ..Default::default()
+                    },
+                    exchange_rate_provider,
+                    historical_exchange_rates,
+                    ppp_conversion_factors,
+                )
+                .await
+            {
+                cache.currency_errors.insert(currency.clone(), err);
+            }
+        }
+        cache
+    }
+
+    /// Convert each country's schedule with `options` into `cache`, ready to evaluate incomes
+    /// up to its highest income. Schedules are keyed by `options.normalizing_currency`.
+    /// Returns the currency amounts are expressed in.
+    pub async fn cache_schedules<'a>(
+        &self,
+        cache: &mut ScheduleCache,
+        max_incomes: impl IntoIterator<Item = (&'a str, f32)>,
+        options: &ExchangeRateOptions,
+        exchange_rate_provider: &dyn ExchangeRateProvider,
+        historical_exchange_rates: &HistoricalExchangeRates,
+        ppp_conversion_factors: &PppConversionFactors,
+    ) -> Result<Option<String>, TaxError> {
+        let max_incomes: Vec<(&str, f32)> = max_incomes.into_iter().collect();
+        let names: Vec<String> = max_incomes
+            .iter()
+            .map(|(country, _)| country.to_string())
+            .collect();
+        let ResolvedExchangeRates {
+            currency,
+            mut exchange_rates,
+            exchange_rates_stale,
+            ..
+        } = self
+            .effective_exchange_rates(
+                &names,
+                options,
+                exchange_rate_provider,
+                historical_exchange_rates,
+                ppp_conversion_factors,
+            )
+            .await?;
+        cache.exchange_rates_stale |= exchange_rates_stale;
+        for (country, max_income) in max_incomes {
+            let exchange_rate = exchange_rates
+                .swap_remove(country)
+                .ok_or_else(|| TaxError::UnknownCountry(country.to_string()))?;
+            let schedule =
+                CachedSchedule::new(self.country_schedule(country)?, exchange_rate, max_income);
+            cache.schedules.insert(
+                (country.to_string(), options.normalizing_currency.clone()),
+                schedule,
+            );
+        }
+        Ok(currency)
+    }
+}
@@ -1,30 +1,193 @@
 use crate::controller::handle_request::TaxPlotDataResponse;
+use crate::controller::uncertainty::{
+    breakeven_uncertainty_bands, tax_uncertainty_bands, validate_uncertainty_evaluations,
+    BreakevenUncertaintyBands, UncertaintyBands,
+};
+use crate::core::grid::IncomeGrid;
+use crate::core::pay_periods::{pay_period_breakdown, PayPeriodAmounts, WithholdingRounding};
 use crate::core::points::marginal_rate_knot::MarginalRateKnot;
-use crate::core::schedules::marginal_schedule::MarginalIncomeTaxRateSchedule;
-use crate::exchange_rates::{fetch_exchange_rates, get_currency_country_mapping};
-use crate::utils::{adjust_exchange_rate_schedule, compute_effective_tax_rates, generate_range};
+use crate::core::points::tax_amount::IncomeTaxKnot;
+use crate::core::sampling::DEFAULT_MAX_EFFECTIVE_RATE_ERROR;
+use crate::core::schedules::amount_schedule::IncomeTaxAmountSchedule;
+use crate::core::schedules::marginal_schedule::{BracketTax, MarginalIncomeTaxRateSchedule};
+use crate::core::segment::EffectiveRateSegment;
+use crate::errors::{ConfigError, ConfigIssue, TaxError};
+use crate::exchange_rates::historical::HistoricalExchangeRates;
+use crate::exchange_rates::ppp::PppConversionFactors;
+use crate::exchange_rates::{ExchangeRateProvider, ExchangeRateTable};
+use crate::utils::{
+    adjust_exchange_rate_schedule, compute_breakevens_excluding_origin, compute_effective_tax_rates,
+};
+use chrono::NaiveDate;
+use indexmap::IndexMap;
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
+use utoipa::ToSchema;
 
-use super::handle_request::TaxPlotDataRequest;
+use super::handle_request::{
+    ExchangeRateOptions, GridSpacing, Normalization, Representation, TaxPlotDataRequest,
+};
+
+/// Highest gross income the inverse solvers search up to.
+pub const MAX_GROSS_INCOME: f32 = 1e9;
+/// Largest number of incomes a grid may have.
+const MAX_GRID_INCOMES: usize = 200000;
+/// Default `step` of linear grids, in units of the currency.
+const DEFAULT_LINEAR_STEP: f32 = 10.0;
+/// Default `step` of log grids, in decades, i.e. 100 incomes per tenfold increase.
+const DEFAULT_LOG_STEP: f32 = 0.01;
+/// Smallest `max_effective_rate_error` accepted, which bounds the number of sampled incomes.
+const MIN_MAX_EFFECTIVE_RATE_ERROR: f32 = 1e-6;
+
+/// Relative difference in tax amounts below which two countries are treated as taxing the same.
+const BREAKEVEN_TAX_TOLERANCE: f32 = 1e-5;
 
 /// A taxes config represents all information available.
 #[derive(Deserialize, Debug, Clone)]
 pub struct TaxesConfig {
     /// Mapping from country to its tax schedule.
-    pub country_map: HashMap<String, MarginalIncomeTaxRateSchedule>,
+    pub country_map: HashMap<String, CountryTaxes>,
+}
+
+/// Tax information for a single country.
+#[derive(Deserialize, Debug, Clone)]
+pub struct CountryTaxes {
+    /// ISO 4217 code of the currency the tax schedule is expressed in.
+    pub currency: String,
+    #[serde(default)]
+    pub metadata: CountryMetadata,
+    /// Rounding of the tax withheld each pay period, exact when absent.
+    #[serde(default)]
+    pub withholding_rounding: Option<WithholdingRounding>,
+    #[serde(flatten)]
+    pub tax_schedule: MarginalIncomeTaxRateSchedule,
+}
+
+/// Optional descriptive information about a country's schedule.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, ToSchema)]
+pub struct CountryMetadata {
+    /// Tax year the schedule applies to, e.g. `2024/25`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tax_year: Option<String>,
+    /// Where the schedule was taken from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
 }
+
 impl TaxesConfig {
+    /// Read and validate a taxes config, panicking if it cannot be used.
     pub fn new(config_path: &str) -> TaxesConfig {
+        TaxesConfig::load(config_path).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Read and validate a taxes config.
+    pub fn load(config_path: &str) -> Result<TaxesConfig, ConfigError> {
         let file = fs::File::open(config_path)
-            .expect(format!("File should open read only, reading {}", config_path).as_str());
-        let json: TaxesConfig = serde_json::from_reader(file).expect("JSON was not well formatted");
-        json
+            .map_err(|err| ConfigError::Io(format!("Reading {}: {}", config_path, err)))?;
+        let config: TaxesConfig = serde_json::from_reader(file)
+            .map_err(|err| ConfigError::Parse(format!("Parsing {}: {}", config_path, err)))?;
+        let issues = config.validate();
+        if !issues.is_empty() {
+            return Err(ConfigError::Invalid(issues));
+        }
+        Ok(config)
+    }
+
+    /// Check every country's currency and brackets, collecting all the problems found.
+    /// Thresholds must ascend, rates must be finite, and only the last bracket may be unbounded.
+    pub fn validate(&self) -> Vec<ConfigIssue> {
+        let mut countries: Vec<&String> = self.country_map.keys().collect();
+        countries.sort();
+        let mut issues = Vec::new();
+        for country in countries {
+            let country_taxes = &self.country_map[country];
+            let mut issue = |bracket: Option<usize>, message: String| {
+                issues.push(ConfigIssue {
+                    country: country.clone(),
+                    bracket,
+                    message,
+                })
+            };
+            let currency = &country_taxes.currency;
+            if !(currency.len() == 3 && currency.chars().all(|c| c.is_ascii_uppercase())) {
+                issue(
+                    None,
+                    format!(
+                        "currency {:?} is not a three letter ISO 4217 code",
+                        currency
+                    ),
+                );
+            }
+            let schedule = country_taxes.tax_schedule.schedule();
+            if schedule.is_empty() {
+                issue(None, "schedule has no brackets".to_string());
+            }
+            if let Some(rounding) = &country_taxes.withholding_rounding {
+                if !(rounding.increment.is_finite() && rounding.increment > 0.0) {
+                    issue(
+                        None,
+                        format!(
+                            "withholding rounding increment {} is not positive",
+                            rounding.increment
+                        ),
+                    );
+                }
+            }
+            let mut previous_limit: Option<f32> = None;
+            for (i, knot) in schedule.iter().enumerate() {
+                if !knot.marginal_rate().is_finite() {
+                    issue(
+                        Some(i),
+                        format!("marginal rate {} is not finite", knot.marginal_rate()),
+                    );
+                }
+                let limit = knot.income_limit().unwrap_or(f32::INFINITY);
+                if limit.is_nan() || limit < 0.0 {
+                    issue(
+                        Some(i),
+                        format!("income limit {} is not a non-negative number", limit),
+                    );
+                } else if limit == f32::INFINITY && i + 1 != schedule.len() {
+                    issue(
+                        Some(i),
+                        "only the last bracket may be unbounded".to_string(),
+                    );
+                } else if let Some(previous) = previous_limit
+                    // An unbounded previous bracket has already been reported.
+                    .filter(|previous| previous.is_finite() && limit <= *previous)
+                {
+                    issue(
+                        Some(i),
+                        format!("income limit {} does not ascend from {}", limit, previous),
+                    );
+                }
+                previous_limit = Some(limit);
+            }
+        }
+        issues
     }
+
     pub fn get_country(&self, country: &str) -> Option<&MarginalIncomeTaxRateSchedule> {
-        self.country_map.get(country)
+        self.country_map
+            .get(country)
+            .map(|country_taxes| &country_taxes.tax_schedule)
+    }
+    /// Tax schedule of a country, or an error naming it when it is not configured.
+    pub fn country_schedule(
+        &self,
+        country: &str,
+    ) -> Result<&MarginalIncomeTaxRateSchedule, TaxError> {
+        self.get_country(country)
+            .ok_or_else(|| TaxError::UnknownCountry(country.to_string()))
+    }
+    pub fn get_currency(&self, country: &str) -> Option<&str> {
+        self.country_map
+            .get(country)
+            .map(|country_taxes| country_taxes.currency.as_str())
     }
 
     /// Process breakeven points
@@ -33,75 +196,128 @@ impl TaxesConfig {
         country_one: &str,
         country_two: &str,
         max_income_to_consider: f32,
-        exchange_rate_config: &Option<HashMap<String, f32>>,
-        country_currency_mapping: &HashMap<&'static str, &'static str>,
-    ) -> BreakevenData {
-        // TODO: Handle exchange rates in a cleaner way
-        let exchange_rate_one = match exchange_rate_config {
-            Some(exchange_rates) => exchange_rates[country_currency_mapping[country_one]],
-            None => 1.0,
-        };
-        let exchange_rate_two = match exchange_rate_config {
-            Some(exchange_rates) => exchange_rates[country_currency_mapping[country_two]],
-            None => 1.0,
-        };
+        exchange_rates: &IndexMap<String, EffectiveExchangeRate>,
+    ) -> Result<BreakevenData, TaxError> {
+        let exchange_rate_one = exchange_rates
+            .get(country_one)
+            .ok_or_else(|| TaxError::UnknownCountry(country_one.to_string()))?
+            .rate;
+        let exchange_rate_two = exchange_rates
+            .get(country_two)
+            .ok_or_else(|| TaxError::UnknownCountry(country_two.to_string()))?
+            .rate;
         let schedule_one = adjust_exchange_rate_schedule(
-            &self,
-            &country_one,
+            self,
+            country_one,
             &Some(exchange_rate_one),
             max_income_to_consider,
-        );
+        )?;
         let schedule_two = adjust_exchange_rate_schedule(
-            &self,
-            &country_two,
+            self,
+            country_two,
             &Some(exchange_rate_two),
             max_income_to_consider,
-        );
-        let breakevens = schedule_one.compute_breakeven_taxes(&schedule_two);
+        )?;
+        let breakevens = compute_breakevens_excluding_origin(&schedule_one, &schedule_two);
         let (breakeven_incomes, breakeven_amounts): (Vec<f32>, Vec<f32>) = breakevens
             .par_iter()
-            // The origin is not interesting, so filter it out
-            .filter(|point| !(point.income() == 0.0 && point.income_tax_amount() == 0.0))
             .map(|point| (point.income(), point.income_tax_amount()))
             .unzip();
 
-        BreakevenData {
-            breakeven_incomes: breakeven_incomes.clone(),
-            breakeven_tax_amounts: breakeven_amounts.clone(),
-            breakeven_effective_tax_rates: compute_effective_tax_rates(
-                &breakeven_incomes,
-                &breakeven_amounts,
-            ),
-        }
+        let breakeven_effective_tax_rates =
+            compute_effective_tax_rates(&breakeven_incomes, &breakeven_amounts);
+        // Probe halfway to the neighbouring breakevens, or to 0 and the max income at the ends.
+        // Breakevens at a segment boundary can be reported twice, so only distinct ones count.
+        let cheaper_at = |income: f32| {
+            cheaper_country(
+                (country_one, &schedule_one),
+                (country_two, &schedule_two),
+                income,
+            )
+        };
+        let (cheaper_below, cheaper_above) = breakeven_incomes
+            .iter()
+            .map(|&income| {
+                let below = breakeven_incomes
+                    .iter()
+                    .copied()
+                    .filter(|&other| other < income)
+                    .fold(0.0, f32::max);
+                let above = breakeven_incomes
+                    .iter()
+                    .copied()
+                    .filter(|&other| other > income)
+                    .fold(max_income_to_consider, f32::min);
+                (
+                    cheaper_at((below + income) / 2.0),
+                    cheaper_at((income + above) / 2.0),
+                )
+            })
+            .unzip();
+        Ok(BreakevenData {
+            country_a: country_one.to_string(),
+            country_b: country_two.to_string(),
+            breakeven_incomes,
+            breakeven_tax_amounts: breakeven_amounts,
+            breakeven_effective_tax_rates,
+            cheaper_below,
+            cheaper_above,
+            uncertainty: None,
+        })
     }
 
     /// Process taxes for a country
     fn process_country_taxes(
         &self,
         country: &str,
-        currency: &Option<String>, // kind of a hack
-        incomes_to_compute: &[f32],
-        max_income: f32,
-        specific_income: Option<f32>,
-        exchange_rate_config: &Option<HashMap<String, f32>>, // feels like a hack having base
-        // currency there too.
-        country_currency_mapping: &HashMap<&'static str, &'static str>,
-    ) -> TaxData {
-        let exchange_rate = match exchange_rate_config {
-            Some(exchange_rates) => exchange_rates[country_currency_mapping[country]],
-            None => 1.0,
-        };
+        req: &TaxPlotDataRequest,
+        income_grid: &IncomeGrid,
+        exchange_rate: f32,
+        currency: &Option<String>,
+    ) -> Result<TaxData, TaxError> {
         // TODO: We can move this to somewhere else not utils
         let schedule =
-            adjust_exchange_rate_schedule(&self, &country, &Some(exchange_rate), max_income);
-        let tax_amounts = match schedule.compute_income_taxes(&incomes_to_compute) {
-            Ok(value) => value,
-            Err(err) => panic!("Error {:?}", err),
-        };
-        let effective_tax_rates = compute_effective_tax_rates(&incomes_to_compute, &tax_amounts);
+            adjust_exchange_rate_schedule(self, country, &Some(exchange_rate), req.max_income)?;
+        let (incomes, tax_amounts, effective_tax_rates, tax_amount_knots, effective_rate_segments) =
+            match req.representation {
+                Representation::Dense => {
+                    let incomes = income_grid.incomes(&schedule);
+                    let tax_amounts = schedule.compute_income_taxes(&incomes)?;
+                    let effective_tax_rates = compute_effective_tax_rates(&incomes, &tax_amounts);
+                    (incomes, tax_amounts, effective_tax_rates, None, None)
+                }
+                Representation::Knots => (
+                    Vec::new(),
+                    Vec::new(),
+                    Vec::new(),
+                    Some(schedule.schedule().clone()),
+                    Some(schedule.effective_rate_segments()),
+                ),
+            };
+        let net_incomes = incomes
+            .iter()
+            .zip(&tax_amounts)
+            .map(|(income, tax_amount)| income - tax_amount)
+            .collect();
 
-        // Get the specific income
-        let specific_tax_amount = schedule.compute_specific_income_tax(specific_income);
+        // Get the specific income. Above `max_income` it has no tax amount, and then none of
+        // its details are reported either.
+        let specific_tax_amount = schedule.compute_specific_income_tax(req.income);
+        let specific_income = req.income.filter(|_| specific_tax_amount.is_some());
+        let marginal_schedule = self
+            .country_schedule(country)?
+            .exchange_rate_adjustment(&Some(exchange_rate));
+        let specific_bracket_taxes = specific_income
+            .zip(specific_tax_amount)
+            .map(|(income, tax_amount)| marginal_schedule.bracket_breakdown(income, tax_amount))
+            .transpose()?;
+        let (specific_marginal_rate, specific_bracket) = match specific_income {
+            Some(income) => (
+                Some(marginal_schedule.marginal_rate_at(income)?),
+                marginal_schedule.bracket_index_at(income)?,
+            ),
+            None => (None, None),
+        };
         let specific_tax_rate = specific_tax_amount.and_then(|tax_amount| {
             specific_income.map(|specific_income| {
                 if specific_income != 0.0 {
@@ -111,80 +327,241 @@ impl TaxesConfig {
                 }
             })
         });
+        let specific_net_income = specific_income
+            .zip(specific_tax_amount)
+            .map(|(income, tax_amount)| income - tax_amount);
+        let pay_periods = specific_income
+            .zip(specific_tax_amount)
+            .map(|(income, tax_amount)| {
+                pay_period_breakdown(
+                    income,
+                    tax_amount,
+                    self.country_map[country].withholding_rounding.as_ref(),
+                    exchange_rate,
+                )
+            });
 
-        TaxData {
+        Ok(TaxData {
             tax_amounts,
             effective_tax_rates,
+            net_incomes,
+            tax_amount_knots,
+            effective_rate_segments,
             // we want to pass back the income so that the plots that use income on client side
             // is always synced up with the backend "compute" income.
             specific_income,
             specific_tax_amount,
             specific_tax_rate,
+            specific_net_income,
+            specific_marginal_rate,
+            specific_bracket,
+            specific_bracket_taxes,
+            pay_periods,
             currency: currency.clone(),
-            incomes: incomes_to_compute.to_vec(),
-            tax_brackets: self.get_country(country).unwrap().schedule().to_vec(),
+            incomes,
+            tax_brackets: self.country_schedule(country)?.schedule().to_vec(),
             exchange_rate: if exchange_rate == 1.0 {
                 None
             } else {
                 Some(exchange_rate)
             },
+            uncertainty: None,
+        })
+    }
+
+    /// Reject requests that would otherwise fail part way through, naming the offending field.
+    fn validate_request(&self, req: &TaxPlotDataRequest) -> Result<(), TaxError> {
+        if let Some(country) = req
+            .countries
+            .iter()
+            .find(|country| !self.country_map.contains_key(*country))
+        {
+            return Err(TaxError::UnknownCountry(country.clone()));
+        }
+        if let Some((_, country)) = req
+            .countries
+            .iter()
+            .enumerate()
+            .find(|(i, country)| req.countries[..*i].contains(country))
+        {
+            return Err(TaxError::invalid_request(
+                "countries",
+                format!("lists {} more than once", country),
+            ));
+        }
+        if !(req.max_income.is_finite() && req.max_income > 0.0) {
+            return Err(TaxError::invalid_request(
+                "max_income",
+                format!("must be positive, got {}", req.max_income),
+            ));
+        }
+        if let Some(income) = req
+            .income
+            .filter(|income| !(income.is_finite() && *income >= 0.0))
+        {
+            return Err(TaxError::invalid_request(
+                "income",
+                format!("must not be negative, got {}", income),
+            ));
         }
+        if req.representation == Representation::Knots && req.exchange_rate_uncertainty.is_some() {
+            return Err(TaxError::unprocessable_request(
+                "representation",
+                "exchange_rate_uncertainty bands are only available for the dense representation",
+            ));
+        }
+        if req.representation == Representation::Knots {
+            let dense_only = [
+                ("incomes", req.incomes.is_some()),
+                ("grid", req.grid.is_some()),
+                ("step", req.step.is_some()),
+                ("min_income", req.min_income.is_some()),
+                (
+                    "max_effective_rate_error",
+                    req.max_effective_rate_error.is_some(),
+                ),
+            ];
+            if let Some((field, _)) = dense_only.iter().find(|(_, given)| *given) {
+                return Err(TaxError::unprocessable_request(
+                    "representation",
+                    format!("{} only applies to the dense representation", field),
+                ));
+            }
+        }
+        Ok(())
     }
 
     /// Process the request to compute taxes information
     pub async fn process_request(
         &self,
         req: &TaxPlotDataRequest,
-    ) -> Result<TaxPlotDataResponse, String> {
-        let step = if req.max_income < 1e6 {10.0} else {100.0}; // simple adaptive step size for
-        // speedup
-        let min_income = 0.0;
-        let incomes_to_compute = generate_range(min_income, req.max_income, step);
-        let country_currency_mapping = get_currency_country_mapping();
-        let exchange_rates_config = match &req.normalizing_currency {
-            Some(currency) => Some(fetch_exchange_rates(&currency).await.unwrap()),
-            None => None,
-        };
-        let country_specific_data: HashMap<String, TaxData> = req
+        exchange_rate_provider: &dyn ExchangeRateProvider,
+        historical_exchange_rates: &HistoricalExchangeRates,
+        ppp_conversion_factors: &PppConversionFactors,
+    ) -> Result<TaxPlotDataResponse, TaxError> {
+        self.validate_request(req)?;
+        let income_grid = income_grid(req)?;
+        let ResolvedExchangeRates {
+            currency,
+            exchange_rates,
+            exchange_rates_stale,
+            exchange_rate_date,
+        } = self
+            .effective_exchange_rates(
+                &req.countries,
+                &req.exchange_rate_options,
+                exchange_rate_provider,
+                historical_exchange_rates,
+                ppp_conversion_factors,
+            )
+            .await?;
+        // Collected into a Vec first so the map keeps the request order.
+        let mut country_specific_data: IndexMap<String, TaxData> = req
             .countries
             .par_iter()
             .map(|country| {
                 let tax_data = self.process_country_taxes(
-                    &country,
-                    &req.normalizing_currency,
-                    &incomes_to_compute,
-                    req.max_income,
-                    req.income,
-                    &exchange_rates_config,
-                    &country_currency_mapping,
-                );
-                (country.clone(), tax_data)
+                    country,
+                    req,
+                    &income_grid,
+                    exchange_rates
+                        .get(country)
+                        .ok_or_else(|| TaxError::UnknownCountry(country.to_string()))?
+                        .rate,
+                    &currency,
+                )?;
+                Ok((country.clone(), tax_data))
             })
+            .collect::<Result<Vec<_>, TaxError>>()?
+            .into_iter()
             .collect();
 
-        let mut country_comb_data = HashMap::new();
+        // Schedules per country under each sampled exchange rate scenario, built once the
+        // incomes are known to be few enough to evaluate under every scenario.
+        let scenario_schedules: Option<HashMap<String, Vec<IncomeTaxAmountSchedule>>> =
+            match &req.exchange_rate_uncertainty {
+                Some(uncertainty) => {
+                    let scenario_rates = self.sample_exchange_rate_scenarios(
+                        uncertainty,
+                        &currency,
+                        exchange_rate_date,
+                        &exchange_rates,
+                        historical_exchange_rates,
+                    )?;
+                    validate_uncertainty_evaluations(
+                        uncertainty.samples,
+                        country_specific_data
+                            .values()
+                            .map(|tax_data| tax_data.incomes.len()),
+                    )?;
+                    Some(
+                        scenario_rates
+                            .into_iter()
+                            .map(|(country, rates)| {
+                                let schedules =
+                                    self.scenario_schedules(&country, &rates, req.max_income)?;
+                                Ok((country, schedules))
+                            })
+                            .collect::<Result<_, TaxError>>()?,
+                    )
+                }
+                None => None,
+            };
+        let percentiles = req
+            .exchange_rate_uncertainty
+            .as_ref()
+            .map(|uncertainty| uncertainty.percentiles.as_slice())
+            .unwrap_or_default();
+        if let Some(scenario_schedules) = &scenario_schedules {
+            // One country at a time, as the bands are already evaluated in parallel.
+            for (country, tax_data) in country_specific_data.iter_mut() {
+                tax_data.uncertainty = Some(tax_uncertainty_bands(
+                    &scenario_schedules[country],
+                    &tax_data.incomes,
+                    percentiles,
+                )?);
+            }
+        }
+
+        let mut country_comb_data = Vec::new();
         if req.show_break_even {
             country_comb_data = req
                 .countries
                 .par_iter()
                 .enumerate()
                 .flat_map(|(i, country_i)| {
-                    req.countries[i + 1..].par_iter().map({
-                        let exchange_rates_config = exchange_rates_config.clone();
-                        let country_currency_mapping = country_currency_mapping.clone();
-                        move |country_j| {
-                            let comb_data = self.process_country_breakeven_points(
-                                country_i,
-                                country_j,
-                                req.max_income,
-                                &exchange_rates_config,
-                                &country_currency_mapping,
-                            );
-                            (format!("{}-{}", country_i, country_j), comb_data)
-                        }
+                    let exchange_rates = &exchange_rates;
+                    let scenario_schedules = &scenario_schedules;
+                    req.countries[i + 1..].par_iter().map(move |country_j| {
+                        let mut comb_data = self.process_country_breakeven_points(
+                            country_i,
+                            country_j,
+                            req.max_income,
+                            exchange_rates,
+                        )?;
+                        comb_data.uncertainty =
+                            scenario_schedules.as_ref().map(|scenario_schedules| {
+                                let breakeven_incomes_by_sample: Vec<Vec<f32>> = scenario_schedules
+                                    [country_i]
+                                    .iter()
+                                    .zip(scenario_schedules[country_j].iter())
+                                    .map(|(schedule_i, schedule_j)| {
+                                        compute_breakevens_excluding_origin(schedule_i, schedule_j)
+                                            .iter()
+                                            .map(|point| point.income())
+                                            .collect()
+                                    })
+                                    .collect();
+                                breakeven_uncertainty_bands(
+                                    &comb_data.breakeven_incomes,
+                                    &breakeven_incomes_by_sample,
+                                    percentiles,
+                                )
+                            });
+                        Ok(comb_data)
                     })
                 })
-                .collect();
+                .collect::<Result<_, TaxError>>()?;
         }
 
         Ok(TaxPlotDataResponse {
@@ -194,38 +571,403 @@ impl TaxesConfig {
             } else {
                 None
             },
+            exchange_rates_stale,
+            exchange_rate_date,
+            exchange_rates,
         })
     }
+
+    /// Fetch, look up or override the exchange rate applied to each of `countries`.
+    pub async fn effective_exchange_rates(
+        &self,
+        countries: &[String],
+        options: &ExchangeRateOptions,
+        exchange_rate_provider: &dyn ExchangeRateProvider,
+        historical_exchange_rates: &HistoricalExchangeRates,
+        ppp_conversion_factors: &PppConversionFactors,
+    ) -> Result<ResolvedExchangeRates, TaxError> {
+        // Rates only need fetching for countries without an override.
+        let needs_fetched_rates = countries.iter().any(|country| {
+            !options
+                .country_exchange_rates
+                .as_ref()
+                .is_some_and(|rates| rates.contains_key(country))
+                && !options.exchange_rates.as_ref().is_some_and(|rates| {
+                    self.get_currency(country)
+                        .is_some_and(|currency| rates.contains_key(currency))
+                })
+        });
+        let ppp = match options.normalization {
+            Normalization::Market => None,
+            Normalization::Ppp => {
+                if options.exchange_rate_date.is_some() {
+                    return Err(TaxError::unprocessable_request(
+                        "exchange_rate_date",
+                        "only applies to market normalization",
+                    ));
+                }
+                match &options.normalizing_currency {
+                    Some(currency) if *currency != ppp_conversion_factors.base_code => {
+                        return Err(TaxError::unprocessable_request(
+                            "normalizing_currency",
+                            format!(
+                                "PPP normalization is expressed in {}, not {}",
+                                ppp_conversion_factors.base_code, currency
+                            ),
+                        ))
+                    }
+                    _ => Some(ppp_conversion_factors),
+                }
+            }
+        };
+        let currency = match ppp {
+            Some(ppp) => Some(ppp.base_code.clone()),
+            None => options.normalizing_currency.clone(),
+        };
+        // Overridden rates are relative to a currency, which amounts must then be reported in.
+        if currency.is_none() {
+            let overrides = [
+                ("country_exchange_rates", &options.country_exchange_rates),
+                ("exchange_rates", &options.exchange_rates),
+            ];
+            if let Some((field, _)) = overrides.iter().find(|(_, rates)| rates.is_some()) {
+                return Err(TaxError::unprocessable_request(
+                    field,
+                    "requires a normalizing_currency",
+                ));
+            }
+        }
+        let (exchange_rate_table, exchange_rate_date) =
+            match (&options.normalizing_currency, options.exchange_rate_date) {
+                (None, Some(_)) => {
+                    return Err(TaxError::unprocessable_request(
+                        "exchange_rate_date",
+                        "requires a normalizing_currency",
+                    ))
+                }
+                (_, Some(_)) if !needs_fetched_rates => {
+                    return Err(TaxError::unprocessable_request(
+                        "exchange_rate_date",
+                        "is unused, as every exchange rate is overridden",
+                    ))
+                }
+                _ if ppp.is_some() || !needs_fetched_rates => (None, None),
+                (Some(currency), Some(date)) => {
+                    let (rates_date, table) = historical_exchange_rates.rates_on(date, currency)?;
+                    (Some(table), Some(rates_date))
+                }
+                (Some(currency), None) => (
+                    Some(
+                        exchange_rate_provider
+                            .fetch_exchange_rates(currency)
+                            .await?,
+                    ),
+                    None,
+                ),
+                (None, None) => (None, None),
+            };
+        Ok(ResolvedExchangeRates {
+            currency,
+            exchange_rates: self.resolve_exchange_rates(
+                countries,
+                options,
+                exchange_rate_table.as_ref(),
+                exchange_rate_date,
+                ppp,
+            )?,
+            exchange_rates_stale: exchange_rate_table
+                .as_ref()
+                .is_some_and(|table| table.stale),
+            exchange_rate_date,
+        })
+    }
+
+    /// Work out the exchange rate applied to each requested country.
+    /// Per-country overrides take precedence over per-currency overrides,
+    /// which take precedence over PPP factors or fetched (or historical) rates.
+    fn resolve_exchange_rates(
+        &self,
+        countries: &[String],
+        options: &ExchangeRateOptions,
+        exchange_rate_table: Option<&ExchangeRateTable>,
+        exchange_rate_date: Option<NaiveDate>,
+        ppp: Option<&PppConversionFactors>,
+    ) -> Result<IndexMap<String, EffectiveExchangeRate>, TaxError> {
+        let overrides = [
+            ("country_exchange_rates", &options.country_exchange_rates),
+            ("exchange_rates", &options.exchange_rates),
+        ];
+        for (field, rates) in overrides {
+            for (key, rate) in rates.iter().flatten() {
+                if !(rate.is_finite() && *rate > 0.0) {
+                    return Err(TaxError::invalid_request(
+                        field,
+                        format!("rate for {} must be positive, got {}", key, rate),
+                    ));
+                }
+            }
+        }
+        countries
+            .iter()
+            .map(|country| {
+                let currency = self
+                    .get_currency(country)
+                    .ok_or_else(|| TaxError::UnknownCountry(country.clone()))?;
+                let country_override = options
+                    .country_exchange_rates
+                    .as_ref()
+                    .and_then(|rates| rates.get(country));
+                let currency_override = options
+                    .exchange_rates
+                    .as_ref()
+                    .and_then(|rates| rates.get(currency));
+                let effective_rate = match (country_override, currency_override) {
+                    (Some(&rate), _) => EffectiveExchangeRate {
+                        rate,
+                        source: ExchangeRateSource::CountryOverride,
+                    },
+                    (None, Some(&rate)) => EffectiveExchangeRate {
+                        rate,
+                        source: ExchangeRateSource::CurrencyOverride,
+                    },
+                    (None, None) => match (ppp, exchange_rate_table) {
+                        (Some(ppp), _) => EffectiveExchangeRate {
+                            rate: ppp.factor(country)?,
+                            source: ExchangeRateSource::Ppp,
+                        },
+                        (None, Some(table)) => EffectiveExchangeRate {
+                            rate: table.rate(currency)?,
+                            source: match exchange_rate_date {
+                                Some(_) => ExchangeRateSource::Historical,
+                                None => ExchangeRateSource::Latest,
+                            },
+                        },
+                        (None, None) => EffectiveExchangeRate {
+                            rate: 1.0,
+                            source: ExchangeRateSource::Unadjusted,
+                        },
+                    },
+                };
+                Ok((country.clone(), effective_rate))
+            })
+            .collect()
+    }
+}
+
+/// Incomes the request's dense curves are evaluated at.
+/// An explicit `incomes` list wins, then a `grid`, and otherwise incomes are sampled adaptively.
+fn income_grid(req: &TaxPlotDataRequest) -> Result<IncomeGrid, TaxError> {
+    let min_income = req.min_income.unwrap_or(0.0);
+    if !(min_income.is_finite() && min_income >= 0.0 && min_income < req.max_income) {
+        return Err(TaxError::invalid_request(
+            "min_income",
+            format!(
+                "must be at least 0 and below max_income, got {}",
+                min_income
+            ),
+        ));
+    }
+    if let Some(max_error) = req
+        .max_effective_rate_error
+        .filter(|max_error| !(max_error.is_finite() && *max_error >= MIN_MAX_EFFECTIVE_RATE_ERROR))
+    {
+        return Err(TaxError::invalid_request(
+            "max_effective_rate_error",
+            format!(
+                "must be at least {}, got {}",
+                MIN_MAX_EFFECTIVE_RATE_ERROR, max_error
+            ),
+        ));
+    }
+    if let Some(step) = req.step.filter(|step| !(step.is_finite() && *step > 0.0)) {
+        return Err(TaxError::invalid_request(
+            "step",
+            format!("must be positive, got {}", step),
+        ));
+    }
+    let income_grid = match (&req.incomes, req.grid) {
+        (Some(_), Some(_)) => {
+            return Err(TaxError::invalid_request(
+                "incomes",
+                "cannot be combined with grid",
+            ))
+        }
+        (Some(incomes), None) => {
+            if incomes.is_empty() || incomes.len() > MAX_GRID_INCOMES {
+                return Err(TaxError::invalid_request(
+                    "incomes",
+                    format!("must have between 1 and {} entries", MAX_GRID_INCOMES),
+                ));
+            }
+            if let Some(income) = incomes
+                .iter()
+                .find(|income| !(income.is_finite() && (0.0..=req.max_income).contains(*income)))
+            {
+                return Err(TaxError::invalid_request(
+                    "incomes",
+                    format!("must be between 0 and max_income, got {}", income),
+                ));
+            }
+            let mut incomes = incomes.clone();
+            incomes.sort_by(f32::total_cmp);
+            incomes.dedup();
+            IncomeGrid::Explicit(incomes)
+        }
+        (None, Some(GridSpacing::Linear)) => IncomeGrid::linear(
+            min_income,
+            req.max_income,
+            req.step.unwrap_or(DEFAULT_LINEAR_STEP),
+        )?,
+        (None, Some(GridSpacing::Log)) => IncomeGrid::log(
+            min_income,
+            req.max_income,
+            req.step.unwrap_or(DEFAULT_LOG_STEP),
+        )?,
+        (None, None) => IncomeGrid::Adaptive {
+            min_income,
+            max_error: req
+                .max_effective_rate_error
+                .unwrap_or(DEFAULT_MAX_EFFECTIVE_RATE_ERROR),
+        },
+    };
+    if let Some(len) = income_grid.len_hint().filter(|len| *len > MAX_GRID_INCOMES) {
+        return Err(TaxError::invalid_request(
+            "step",
+            format!(
+                "grid would have {} incomes, more than the {} allowed",
+                len, MAX_GRID_INCOMES
+            ),
+        ));
+    }
+    Ok(income_grid)
+}
+
+/// The country taxing `income` less, or `None` when both tax it the same.
+fn cheaper_country(
+    (country_one, schedule_one): (&str, &IncomeTaxAmountSchedule),
+    (country_two, schedule_two): (&str, &IncomeTaxAmountSchedule),
+    income: f32,
+) -> Option<String> {
+    let tax_one = schedule_one.compute_specific_income_tax(Some(income))?;
+    let tax_two = schedule_two.compute_specific_income_tax(Some(income))?;
+    let tolerance = BREAKEVEN_TAX_TOLERANCE * tax_one.abs().max(tax_two.abs()).max(1.0);
+    if (tax_one - tax_two).abs() <= tolerance {
+        None
+    } else if tax_one < tax_two {
+        Some(country_one.to_string())
+    } else {
+        Some(country_two.to_string())
+    }
 }
 
 // Other structs linked to TaxesConfig
-#[derive(Serialize)]
+/// Where the exchange rate applied to a country came from.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExchangeRateSource {
+    /// No normalizing currency, amounts are left in the country's own currency.
+    Unadjusted,
+    Latest,
+    Historical,
+    /// Purchasing power parity conversion factor.
+    Ppp,
+    CurrencyOverride,
+    CountryOverride,
+}
+
+/// Exchange rates applied to a set of countries, and how they were obtained.
+pub struct ResolvedExchangeRates {
+    /// Currency that amounts are expressed in, `None` to leave them in each country's own.
+    pub currency: Option<String>,
+    /// Keyed by country, in request order.
+    pub exchange_rates: IndexMap<String, EffectiveExchangeRate>,
+    pub exchange_rates_stale: bool,
+    /// Date of the historical rates used, if any.
+    pub exchange_rate_date: Option<NaiveDate>,
+}
+
+/// The exchange rate applied to a country, in units of its currency per normalizing currency.
+#[derive(Clone, Debug, PartialEq, Serialize, ToSchema)]
+pub struct EffectiveExchangeRate {
+    pub rate: f32,
+    pub source: ExchangeRateSource,
+}
+
+/// Incomes where two countries tax the same, for the pair `country_a` and `country_b`.
+#[derive(Serialize, ToSchema)]
 pub struct BreakevenData {
+    pub country_a: String,
+    pub country_b: String,
     pub breakeven_incomes: Vec<f32>,
     pub breakeven_tax_amounts: Vec<f32>,
     pub breakeven_effective_tax_rates: Vec<f32>,
+    /// Country taxing less just below each breakeven income, `None` where they tax the same.
+    pub cheaper_below: Vec<Option<String>>,
+    /// Country taxing less just above each breakeven income, `None` where they tax the same.
+    pub cheaper_above: Vec<Option<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uncertainty: Option<BreakevenUncertaintyBands>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct TaxData {
+    /// Sampled curves, left out of the `knots` representation.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
     pub incomes: Vec<f32>,
-    pub tax_amounts: Vec<f32>, // TODO: tax amounts not needed can just use knot points.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tax_amounts: Vec<f32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
     pub effective_tax_rates: Vec<f32>,
+    /// Income less tax at each of `incomes`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub net_incomes: Vec<f32>,
+    /// Knots of the tax amount curve up to `max_income`, in the `knots` representation.
+    /// Tax amounts are linear between consecutive knots.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tax_amount_knots: Option<Vec<IncomeTaxKnot>>,
+    /// Exact effective rate curve per segment between knots, in the `knots` representation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effective_rate_segments: Option<Vec<EffectiveRateSegment>>,
+    /// Tax on `specific_income`. This and every other `specific_*` field is absent together
+    /// when no `income` is requested or it is above `max_income`.
     pub specific_tax_amount: Option<f32>,
     pub specific_tax_rate: Option<f32>,
+    pub specific_net_income: Option<f32>,
+    /// Rate paid on the next unit of income above `specific_income`.
+    pub specific_marginal_rate: Option<f32>,
+    /// Index into `tax_brackets` of the bracket `specific_marginal_rate` comes from.
+    pub specific_bracket: Option<usize>,
+    /// Tax each of `tax_brackets` contributes at `specific_income`, summing exactly to
+    /// `specific_tax_amount` and present whenever it is. Thresholds are in the normalizing
+    /// currency.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub specific_bracket_taxes: Option<Vec<BracketTax>>,
+    /// Pay and tax of `specific_income` over each pay period.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pay_periods: Option<Vec<PayPeriodAmounts>>,
     pub tax_brackets: Vec<MarginalRateKnot>,
     pub exchange_rate: Option<f32>,
     pub specific_income: Option<f32>,
     pub currency: Option<String>,
+    /// Percentile bands over sampled exchange rates, when requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uncertainty: Option<UncertaintyBands>,
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::controller::taxes_config::TaxesConfig;
+    use crate::controller::handle_request::TaxPlotDataResponse;
+    use crate::controller::taxes_config::{EffectiveExchangeRate, ExchangeRateSource, TaxesConfig};
+    use crate::controller::test_fixtures::Fixture;
+    use crate::errors::{ConfigError, ConfigIssue, TaxError};
+    use crate::exchange_rates::static_provider::StaticExchangeRateProvider;
+    use indexmap::IndexMap;
+    use serde_json::json;
+
     #[test]
     fn test_taxes_config() {
         let file_path = "test_data/valid_config.json";
-        let taxes_config = TaxesConfig::new(&file_path);
+        let taxes_config = TaxesConfig::new(file_path);
 
         assert_eq!(taxes_config.country_map.len(), 2);
         assert!(taxes_config.country_map.contains_key("New Zealand"));
@@ -236,6 +978,7 @@ mod tests {
                 .country_map
                 .get("New Zealand")
                 .unwrap()
+                .tax_schedule
                 .schedule()
                 .len(),
             5
@@ -245,9 +988,666 @@ mod tests {
                 .country_map
                 .get("Australia")
                 .unwrap()
+                .tax_schedule
                 .schedule()
                 .len(),
             5
         );
+        assert_eq!(taxes_config.get_currency("New Zealand"), Some("NZD"));
+        assert_eq!(taxes_config.get_currency("Australia"), Some("AUD"));
+        assert_eq!(taxes_config.get_currency("Foo"), None);
+    }
+
+    #[test]
+    fn test_zero_width_first_bracket() {
+        // Both countries' first bracket ends at zero income.
+        let taxes_config = TaxesConfig::new("test_data/valid_config.json");
+        for country in ["New Zealand", "Australia"] {
+            let schedule = taxes_config
+                .country_schedule(country)
+                .unwrap()
+                .to_income_amount_schedule(100000.0);
+            assert_eq!(schedule.compute_income_taxes(&[0.0]), Ok(vec![0.0]));
+        }
+    }
+
+    #[test]
+    fn test_taxes_config_requires_currency() {
+        match TaxesConfig::load("test_data/missing_currency.json") {
+            Err(ConfigError::Parse(msg)) => assert!(msg.contains("missing field `currency`")),
+            other => panic!("Expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_taxes_config_load_errors() {
+        assert!(matches!(
+            TaxesConfig::load("test_data/does_not_exist.json"),
+            Err(ConfigError::Io(_))
+        ));
+        assert_eq!(
+            TaxesConfig::load("test_data/invalid_config.json").unwrap_err(),
+            ConfigError::Invalid(vec![
+                ConfigIssue {
+                    country: "Atlantis".to_string(),
+                    bracket: None,
+                    message: "currency \"atl\" is not a three letter ISO 4217 code".to_string(),
+                },
+                ConfigIssue {
+                    country: "Atlantis".to_string(),
+                    bracket: Some(1),
+                    message: "only the last bracket may be unbounded".to_string(),
+                },
+                ConfigIssue {
+                    country: "Lemuria".to_string(),
+                    bracket: None,
+                    message: "withholding rounding increment 0 is not positive".to_string(),
+                },
+                ConfigIssue {
+                    country: "Lemuria".to_string(),
+                    bracket: Some(2),
+                    message: "income limit 10000 does not ascend from 20000".to_string(),
+                },
+            ])
+        );
+        assert!(TaxesConfig::load("assets/taxes.json").is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_process_request_with_static_exchange_rates() {
+        let fixture = Fixture::new("test_data/valid_config.json");
+        let response = fixture
+            .process(json!({"normalizing_currency": "NZD"}))
+            .await
+            .unwrap();
+        let australia = &response.country_specific_data["Australia"];
+        assert_eq!(australia.exchange_rate, Some(0.9));
+        assert_eq!(australia.currency, Some("NZD".to_string()));
+        assert_eq!(
+            response.country_specific_data["New Zealand"].exchange_rate,
+            None
+        );
+        let pairs = response.country_comb_data.unwrap();
+        assert_eq!(pairs.len(), 1);
+        assert_eq!(pairs[0].country_a, "New Zealand");
+        assert_eq!(pairs[0].country_b, "Australia");
+        assert_eq!(response.exchange_rate_date, None);
+
+        // No rates for the base currency is an error rather than a panic.
+        assert!(fixture
+            .process(json!({"normalizing_currency": "USD"}))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_process_request_specific_income_details() {
+        let fixture = Fixture::new("test_data/valid_config.json");
+        let response = fixture
+            .process(json!({"normalizing_currency": "NZD"}))
+            .await
+            .unwrap();
+        for tax_data in response.country_specific_data.values() {
+            assert_eq!(
+                tax_data.net_incomes,
+                tax_data
+                    .incomes
+                    .iter()
+                    .zip(&tax_data.tax_amounts)
+                    .map(|(income, tax_amount)| income - tax_amount)
+                    .collect::<Vec<_>>()
+            );
+            assert_eq!(tax_data.incomes[0], 0.0);
+            assert_eq!(tax_data.net_incomes[0], 0.0);
+            let specific_tax_amount = tax_data.specific_tax_amount.unwrap();
+            assert_eq!(
+                tax_data.specific_net_income,
+                Some(50000.0 - specific_tax_amount)
+            );
+            let pay_periods = tax_data.pay_periods.as_ref().unwrap();
+            assert_eq!(pay_periods[0].tax_amount, specific_tax_amount);
+            assert_eq!(pay_periods[3].gross_income, 50000.0 / 52.0);
+        }
+
+        // The bracket breakdown adds up to the tax amount exactly.
+        let new_zealand = &response.country_specific_data["New Zealand"];
+        let brackets = new_zealand.specific_bracket_taxes.as_ref().unwrap();
+        assert_eq!(brackets.len(), new_zealand.tax_brackets.len());
+        assert_eq!(
+            brackets
+                .iter()
+                .map(|bracket| bracket.tax_amount)
+                .sum::<f32>(),
+            new_zealand.specific_tax_amount.unwrap()
+        );
+        assert_eq!(
+            brackets
+                .iter()
+                .map(|bracket| bracket.income_taxed)
+                .sum::<f32>(),
+            50000.0
+        );
+        assert_eq!(new_zealand.specific_bracket, Some(3));
+        assert_eq!(new_zealand.specific_marginal_rate, Some(0.33));
+        // Australia's thresholds are converted to New Zealand dollars.
+        let australia = &response.country_specific_data["Australia"];
+        let brackets = australia.specific_bracket_taxes.as_ref().unwrap();
+        assert_eq!(brackets[2].income_start, 18200.0 / 0.9);
+        assert_eq!(australia.specific_marginal_rate, Some(0.3));
+
+        // New Zealand withholds exactly, Australia in whole Australian dollars.
+        let new_zealand = &response.country_specific_data["New Zealand"];
+        let weekly_tax = new_zealand.pay_periods.as_ref().unwrap()[3].tax_amount;
+        assert_eq!(weekly_tax, new_zealand.specific_tax_amount.unwrap() / 52.0);
+        let australia = &response.country_specific_data["Australia"];
+        let weekly_tax = australia.pay_periods.as_ref().unwrap()[3].tax_amount;
+        assert!(((weekly_tax * 0.9).round() - weekly_tax * 0.9).abs() < 1e-3);
+        assert!((weekly_tax - australia.specific_tax_amount.unwrap() / 52.0).abs() <= 0.5 / 0.9);
+    }
+
+    #[tokio::test]
+    async fn test_process_request_specific_income_above_max_income() {
+        let fixture = Fixture::new("test_data/valid_config.json");
+        let response = fixture.process(json!({"income": 250000.0})).await.unwrap();
+        for tax_data in response.country_specific_data.values() {
+            assert_eq!(tax_data.specific_income, None);
+            assert_eq!(tax_data.specific_tax_amount, None);
+            assert_eq!(tax_data.specific_tax_rate, None);
+            assert_eq!(tax_data.specific_net_income, None);
+            assert_eq!(tax_data.specific_marginal_rate, None);
+            assert_eq!(tax_data.specific_bracket, None);
+            assert!(tax_data.specific_bracket_taxes.is_none());
+            assert!(tax_data.pay_periods.is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_process_request_keeps_request_order() {
+        let fixture = Fixture::new("test_data/valid_config.json");
+        for countries in [["Australia", "New Zealand"], ["New Zealand", "Australia"]] {
+            let patch = json!({
+                "countries": countries,
+                "normalizing_currency": "NZD",
+            });
+            let response = fixture.process(patch.clone()).await.unwrap();
+            let order: Vec<&String> = response.country_specific_data.keys().collect();
+            assert_eq!(order, countries);
+            let order: Vec<&String> = response.exchange_rates.keys().collect();
+            assert_eq!(order, countries);
+
+            // Identical requests serialise identically.
+            let again = fixture.process(patch).await.unwrap();
+            assert_eq!(
+                serde_json::to_string(&response).unwrap(),
+                serde_json::to_string(&again).unwrap()
+            );
+        }
+
+        // A repeated country would pair with itself.
+        assert_eq!(
+            fixture
+                .process(json!({
+                    "countries": ["Australia", "New Zealand", "Australia"],
+                }))
+                .await
+                .err(),
+            Some(TaxError::invalid_request(
+                "countries",
+                "lists Australia more than once"
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_process_request_adaptive_sampling() {
+        let fixture = Fixture::new("test_data/valid_config.json");
+        let response = fixture.process(json!({})).await.unwrap();
+        let australia = &response.country_specific_data["Australia"];
+        // A step of 10 would need 20001 incomes.
+        assert!(australia.incomes.len() < 1000);
+        assert_eq!(australia.incomes.first(), Some(&0.0));
+        assert_eq!(australia.incomes.last(), Some(&200000.0));
+        assert!(australia.incomes.contains(&18200.0));
+
+        let coarse = fixture
+            .process(json!({"max_effective_rate_error": 0.01}))
+            .await
+            .unwrap();
+        assert!(coarse.country_specific_data["Australia"].incomes.len() < australia.incomes.len());
+
+        assert!(matches!(
+            fixture.process(json!({"max_effective_rate_error": 0.0})).await,
+            Err(TaxError::InvalidRequest { field, .. }) if field == "max_effective_rate_error"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_process_request_income_grids() {
+        let fixture = Fixture::new("test_data/valid_config.json");
+        let incomes = |response: TaxPlotDataResponse| {
+            response.country_specific_data["New Zealand"]
+                .incomes
+                .clone()
+        };
+
+        let linear = fixture
+            .process(json!({"grid": "linear", "min_income": 1000.0, "step": 50000.0}))
+            .await
+            .unwrap();
+        assert_eq!(
+            incomes(linear),
+            vec![1000.0, 51000.0, 101000.0, 151000.0, 200000.0]
+        );
+
+        let log = fixture
+            .process(json!({"grid": "log", "min_income": 2000.0, "step": 1.0}))
+            .await
+            .unwrap();
+        assert_eq!(incomes(log), vec![2000.0, 20000.0, 200000.0]);
+
+        let explicit = fixture
+            .process(json!({"incomes": [60000.0, 1000.0, 60000.0]}))
+            .await
+            .unwrap();
+        let new_zealand = &explicit.country_specific_data["New Zealand"];
+        assert_eq!(new_zealand.incomes, vec![1000.0, 60000.0]);
+        assert_eq!(new_zealand.tax_amounts.len(), 2);
+
+        for (extra, field) in [
+            (json!({"grid": "log"}), "min_income"),
+            (json!({"grid": "linear", "step": 0.001}), "step"),
+            (json!({"min_income": 300000.0}), "min_income"),
+            (json!({"incomes": [250000.0]}), "incomes"),
+            (json!({"incomes": [1.0], "grid": "linear"}), "incomes"),
+        ] {
+            assert!(
+                matches!(
+                    fixture.process(extra.clone()).await,
+                    Err(TaxError::InvalidRequest { field: f, .. }) if f == field
+                ),
+                "{}",
+                extra
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_process_request_with_knots_representation() {
+        let fixture = Fixture::new("test_data/valid_config.json");
+        let dense = fixture.process(json!({})).await.unwrap();
+        let knots = fixture
+            .process(json!({"representation": "knots"}))
+            .await
+            .unwrap();
+
+        let dense = &dense.country_specific_data["Australia"];
+        let knots = &knots.country_specific_data["Australia"];
+        assert!(dense.tax_amount_knots.is_none());
+        assert!(knots.incomes.is_empty() && knots.tax_amounts.is_empty());
+        assert_eq!(knots.specific_tax_amount, dense.specific_tax_amount);
+        let tax_amount_knots = knots.tax_amount_knots.as_ref().unwrap();
+        assert_eq!(tax_amount_knots.last().unwrap().income_limit(), 200000.0);
+
+        // The segments reproduce the dense effective rates.
+        let segments = knots.effective_rate_segments.as_ref().unwrap();
+        for (income, effective_tax_rate) in dense.incomes.iter().zip(&dense.effective_tax_rates) {
+            if *income == 0.0 {
+                continue;
+            }
+            let segment = segments
+                .iter()
+                .find(|segment| segment.income_start <= *income && *income <= segment.income_end)
+                .unwrap();
+            let rate = segment.slope + segment.intercept / income;
+            assert!((rate - effective_tax_rate).abs() < 1e-4, "{}", income);
+        }
+
+        let value = serde_json::to_value(knots).unwrap();
+        assert!(value.get("incomes").is_none());
+
+        let err = fixture
+            .process(json!({
+                "representation": "knots",
+                "normalizing_currency": "NZD",
+                "exchange_rate_uncertainty": {"volatilities": {"AUD": 0.1}}
+            }))
+            .await;
+        assert!(matches!(
+            err,
+            Err(TaxError::UnprocessableRequest { field, .. }) if field == "representation"
+        ));
+
+        // Grid fields shape the dense curves only.
+        for mut fields in [
+            json!({"incomes": [1000.0]}),
+            json!({"grid": "linear"}),
+            json!({"step": 100.0}),
+            json!({"min_income": 1000.0}),
+            json!({"max_effective_rate_error": 0.01}),
+        ] {
+            fields["representation"] = json!("knots");
+            let err = fixture.process(fields).await;
+            assert!(matches!(
+                err,
+                Err(TaxError::UnprocessableRequest { field, .. }) if field == "representation"
+            ));
+        }
+    }
+
+    #[test]
+    fn test_breakeven_cheaper_sides() {
+        let taxes_config = TaxesConfig::load("test_data/valid_config.json").unwrap();
+        let unadjusted = EffectiveExchangeRate {
+            rate: 1.0,
+            source: ExchangeRateSource::Unadjusted,
+        };
+        let exchange_rates = IndexMap::from([
+            ("New Zealand".to_string(), unadjusted.clone()),
+            ("Australia".to_string(), unadjusted),
+        ]);
+        let breakevens = taxes_config
+            .process_country_breakeven_points("New Zealand", "Australia", 300000.0, &exchange_rates)
+            .unwrap();
+        assert_eq!(breakevens.country_a, "New Zealand");
+        assert_eq!(breakevens.country_b, "Australia");
+        let australia = Some("Australia".to_string());
+        let new_zealand = Some("New Zealand".to_string());
+        // Australia's tax free threshold makes it cheaper at first, then the lead alternates.
+        assert_eq!(breakevens.breakeven_incomes.len(), 3);
+        assert_eq!(
+            breakevens.cheaper_below,
+            vec![australia.clone(), new_zealand.clone(), australia.clone()]
+        );
+        assert_eq!(
+            breakevens.cheaper_above,
+            vec![new_zealand.clone(), australia.clone(), new_zealand.clone()]
+        );
+
+        // Swapping the pair swaps the names but not the answer.
+        let swapped = taxes_config
+            .process_country_breakeven_points("Australia", "New Zealand", 300000.0, &exchange_rates)
+            .unwrap();
+        assert_eq!(swapped.cheaper_below, breakevens.cheaper_below);
+        assert_eq!(swapped.cheaper_above, breakevens.cheaper_above);
+    }
+
+    #[tokio::test]
+    async fn test_process_request_with_historical_exchange_rates() {
+        let fixture = Fixture::new("test_data/valid_config.json")
+            .with_historical_rates("test_data/historical_exchange_rates.csv");
+        let response = fixture
+            .process(json!({
+                "normalizing_currency": "NZD",
+                "exchange_rate_date": "2024-03-15",
+            }))
+            .await
+            .unwrap();
+        assert_eq!(response.exchange_rate_date, "2024-01-01".parse().ok());
+        assert_eq!(
+            response.country_specific_data["Australia"].exchange_rate,
+            Some(0.5)
+        );
+
+        // A date needs a currency to normalize to, and rates on or before it.
+        assert!(fixture
+            .process(json!({"exchange_rate_date": "2024-03-15"}))
+            .await
+            .is_err());
+        assert!(fixture
+            .process(json!({
+                "normalizing_currency": "NZD",
+                "exchange_rate_date": "2020-01-01",
+            }))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_process_request_with_exchange_rate_overrides() {
+        // Nothing to fetch from, so every rate must come from the request.
+        let unfetched = Fixture::new("test_data/valid_config.json")
+            .with_provider(StaticExchangeRateProvider::new(vec![]));
+        let response = unfetched
+            .process(json!({
+                "normalizing_currency": "NZD",
+                "exchange_rates": {"NZD": 1.0, "AUD": 0.99},
+            }))
+            .await
+            .unwrap();
+        assert_eq!(
+            response.exchange_rates["Australia"],
+            EffectiveExchangeRate {
+                rate: 0.99,
+                source: ExchangeRateSource::CurrencyOverride
+            }
+        );
+        assert_eq!(
+            response.country_specific_data["Australia"].exchange_rate,
+            Some(0.99)
+        );
+
+        // Country overrides win over currency overrides, the rest are fetched.
+        let fixture = Fixture::new("test_data/valid_config.json");
+        let response = fixture
+            .process(json!({
+                "normalizing_currency": "NZD",
+                "exchange_rates": {"AUD": 0.99},
+                "country_exchange_rates": {"Australia": 0.8},
+            }))
+            .await
+            .unwrap();
+        assert_eq!(
+            response.exchange_rates["Australia"],
+            EffectiveExchangeRate {
+                rate: 0.8,
+                source: ExchangeRateSource::CountryOverride
+            }
+        );
+        assert_eq!(
+            response.exchange_rates["New Zealand"],
+            EffectiveExchangeRate {
+                rate: 1.0,
+                source: ExchangeRateSource::Latest
+            }
+        );
+
+        assert!(fixture
+            .process(json!({
+                "normalizing_currency": "NZD",
+                "country_exchange_rates": {"Australia": -1.0}
+            }))
+            .await
+            .is_err());
+
+        // Without a normalizing currency there is nothing for an override to be relative to.
+        assert_eq!(
+            fixture
+                .process(json!({"country_exchange_rates": {"Australia": 0.8}}))
+                .await
+                .err(),
+            Some(TaxError::unprocessable_request(
+                "country_exchange_rates",
+                "requires a normalizing_currency"
+            ))
+        );
+
+        // A date is rejected rather than silently ignored when no rate is looked up.
+        assert_eq!(
+            fixture
+                .process(json!({
+                    "normalizing_currency": "NZD",
+                    "exchange_rate_date": "2024-01-01",
+                    "exchange_rates": {"NZD": 1.0, "AUD": 0.99},
+                }))
+                .await
+                .err(),
+            Some(TaxError::unprocessable_request(
+                "exchange_rate_date",
+                "is unused, as every exchange rate is overridden"
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_process_request_with_ppp_normalization() {
+        let fixture = Fixture::new("test_data/valid_config.json")
+            .with_ppp_conversion_factors("test_data/ppp_conversion_factors.json");
+        let response = fixture
+            .process(json!({"normalization": "ppp"}))
+            .await
+            .unwrap();
+        let australia = &response.country_specific_data["Australia"];
+        assert_eq!(australia.exchange_rate, Some(1.25));
+        assert_eq!(australia.currency, Some("USD".to_string()));
+        assert_eq!(
+            response.exchange_rates["New Zealand"],
+            EffectiveExchangeRate {
+                rate: 1.5,
+                source: ExchangeRateSource::Ppp
+            }
+        );
+        // Same path as market rates: NZ's first threshold of 14000 NZD is 14000 / 1.5 USD at PPP.
+        assert_eq!(
+            response.country_specific_data["New Zealand"].specific_tax_amount,
+            fixture
+                .taxes_config
+                .get_country("New Zealand")
+                .unwrap()
+                .exchange_rate_adjustment(&Some(1.5))
+                .to_income_amount_schedule(200000.0)
+                .compute_specific_income_tax(Some(50000.0))
+        );
+
+        // PPP amounts are in the factors' base currency.
+        assert!(fixture
+            .process(json!({"normalization": "ppp", "normalizing_currency": "NZD"}))
+            .await
+            .is_err());
+        assert!(fixture
+            .process(json!({"normalization": "ppp", "normalizing_currency": "USD"}))
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_process_request_with_exchange_rate_uncertainty() {
+        let fixture = Fixture::new("test_data/valid_config.json")
+            .with_historical_rates("test_data/historical_exchange_rates.csv");
+        let patch = json!({
+            "normalizing_currency": "NZD",
+            "exchange_rate_uncertainty": {"volatilities": {"AUD": 0.1}, "samples": 100, "seed": 7}
+        });
+        let response = fixture.process(patch.clone()).await.unwrap();
+        // The normalizing currency does not move, so its bands collapse onto the point estimate.
+        let new_zealand = &response.country_specific_data["New Zealand"];
+        let bands = new_zealand.uncertainty.as_ref().unwrap();
+        assert_eq!(bands.percentiles, vec![5.0, 50.0, 95.0]);
+        for band in &bands.tax_amounts {
+            assert_eq!(band, &new_zealand.tax_amounts);
+        }
+        // Australian amounts spread out, and the bands are ordered.
+        let australia = &response.country_specific_data["Australia"];
+        let bands = australia.uncertainty.as_ref().unwrap();
+        let last = australia.incomes.len() - 1;
+        assert!(bands.tax_amounts[0][last] < bands.tax_amounts[1][last]);
+        assert!(bands.tax_amounts[1][last] < bands.tax_amounts[2][last]);
+        let breakeven_bands = response.country_comb_data.as_ref().unwrap()[0]
+            .uncertainty
+            .as_ref()
+            .unwrap();
+        assert_eq!(breakeven_bands.percentiles, vec![5.0, 50.0, 95.0]);
+
+        // Same seed, same bands.
+        let again = fixture.process(patch).await.unwrap();
+        let again_bands = again.country_specific_data["Australia"]
+            .uncertainty
+            .as_ref()
+            .unwrap();
+        for (again_band, band) in again_bands.tax_amounts.iter().zip(bands.tax_amounts.iter()) {
+            assert_eq!(again_band, band);
+        }
+
+        // Volatility estimated from the historical rates.
+        let response = fixture
+            .process(json!({
+                "normalizing_currency": "NZD",
+                "exchange_rate_uncertainty": {"historical_window_days": 365}
+            }))
+            .await
+            .unwrap();
+        assert!(response.country_specific_data["Australia"]
+            .uncertainty
+            .is_some());
+
+        assert!(fixture
+            .process(json!({
+                "normalizing_currency": "NZD",
+                "exchange_rate_uncertainty": {"samples": 0}
+            }))
+            .await
+            .is_err());
+
+        // Two countries of 100001 incomes each under 2000 scenarios is too many tax amounts.
+        assert_eq!(
+            fixture
+                .process(json!({
+                    "normalizing_currency": "NZD",
+                    "grid": "linear",
+                    "step": 2.0,
+                    "exchange_rate_uncertainty": {"volatilities": {"AUD": 0.1}, "samples": 2000}
+                }))
+                .await
+                .err(),
+            Some(TaxError::invalid_request(
+                "exchange_rate_uncertainty.samples",
+                "2000 samples of 200002 incomes is more than the 10000000 tax amounts allowed"
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_process_request_errors() {
+        let fixture = Fixture::new("test_data/valid_config.json");
+        assert_eq!(
+            fixture
+                .process(json!({"countries": ["New Zealand", "Atlantis"]}))
+                .await
+                .err(),
+            Some(TaxError::UnknownCountry("Atlantis".to_string()))
+        );
+        assert_eq!(
+            fixture.process(json!({"max_income": -1.0})).await.err(),
+            Some(TaxError::invalid_request(
+                "max_income",
+                "must be positive, got -1"
+            ))
+        );
+        assert_eq!(
+            fixture
+                .process(json!({"exchange_rates": {"AUD": 0.0}, "normalizing_currency": "NZD"}))
+                .await
+                .err(),
+            Some(TaxError::invalid_request(
+                "exchange_rates",
+                "rate for AUD must be positive, got 0"
+            ))
+        );
+        assert_eq!(
+            fixture
+                .process(json!({"exchange_rate_date": "2024-01-01"}))
+                .await
+                .err(),
+            Some(TaxError::unprocessable_request(
+                "exchange_rate_date",
+                "requires a normalizing_currency"
+            ))
+        );
+        assert_eq!(
+            fixture
+                .process(json!({"normalizing_currency": "USD"}))
+                .await
+                .err(),
+            Some(TaxError::UnknownCurrency("USD".to_string()))
+        );
     }
 }
@@ -0,0 +1,148 @@
+use crate::controller::handle_batch::{parse_batch_rows, BatchResultRow};
+use crate::controller::handle_gross_income::GrossIncomeResponse;
+use crate::controller::handle_request::{TaxPlotDataRequest, TaxPlotDataResponse};
+use crate::controller::handle_salary_equivalence::SalaryEquivalenceResponse;
+use crate::controller::handle_tax::TaxResponse;
+use crate::controller::handle_tie_exchange_rate::TieExchangeRateResponse;
+use crate::controller::taxes_config::TaxesConfig;
+use crate::errors::TaxError;
+use crate::exchange_rates::historical::HistoricalExchangeRates;
+use crate::exchange_rates::ppp::PppConversionFactors;
+use crate::exchange_rates::static_provider::StaticExchangeRateProvider;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+/// A configuration and the exchange rate data requests are processed against in tests.
+/// Rates are New Zealand dollar based, with 0.9 AUD to the NZD, and there are no historical
+/// rates or PPP conversion factors unless added.
+pub struct Fixture {
+    pub taxes_config: TaxesConfig,
+    pub provider: StaticExchangeRateProvider,
+    pub historical: HistoricalExchangeRates,
+    pub ppp: PppConversionFactors,
+}
+
+impl Fixture {
+    pub fn new(config_path: &str) -> Self {
+        Self {
+            taxes_config: TaxesConfig::load(config_path).unwrap(),
+            provider: StaticExchangeRateProvider::from_file("test_data/exchange_rates.json")
+                .unwrap(),
+            historical: HistoricalExchangeRates::default(),
+            ppp: PppConversionFactors::default(),
+        }
+    }
+
+    pub fn with_provider(self, provider: StaticExchangeRateProvider) -> Self {
+        Self { provider, ..self }
+    }
+
+    pub fn with_historical_rates(self, path: &str) -> Self {
+        Self {
+            historical: HistoricalExchangeRates::from_csv_file(path).unwrap(),
+            ..self
+        }
+    }
+
+    pub fn with_ppp_conversion_factors(self, path: &str) -> Self {
+        Self {
+            ppp: PppConversionFactors::from_json_file(path).unwrap(),
+            ..self
+        }
+    }
+
+    /// Process [`nz_au_request`] with the fields of `patch` set over it.
+    pub async fn process(&self, patch: Value) -> Result<TaxPlotDataResponse, TaxError> {
+        self.taxes_config
+            .process_request(
+                &nz_au_request(patch),
+                &self.provider,
+                &self.historical,
+                &self.ppp,
+            )
+            .await
+    }
+
+    pub async fn tax(&self, req: Value) -> Result<TaxResponse, TaxError> {
+        self.taxes_config
+            .process_tax_request(
+                &serde_json::from_value(req).unwrap(),
+                &self.provider,
+                &self.historical,
+                &self.ppp,
+            )
+            .await
+    }
+
+    pub async fn gross_income(&self, req: Value) -> Result<GrossIncomeResponse, TaxError> {
+        self.taxes_config
+            .process_gross_income_request(
+                &serde_json::from_value(req).unwrap(),
+                &self.provider,
+                &self.historical,
+                &self.ppp,
+            )
+            .await
+    }
+
+    pub async fn salary_equivalence(
+        &self,
+        req: Value,
+    ) -> Result<SalaryEquivalenceResponse, TaxError> {
+        self.taxes_config
+            .process_salary_equivalence_request(
+                &serde_json::from_value(req).unwrap(),
+                &self.provider,
+                &self.historical,
+                &self.ppp,
+            )
+            .await
+    }
+
+    pub async fn tie_exchange_rate(&self, req: Value) -> Result<TieExchangeRateResponse, TaxError> {
+        self.taxes_config
+            .process_tie_exchange_rate_request(
+                &serde_json::from_value(req).unwrap(),
+                &self.provider,
+                &self.historical,
+                &self.ppp,
+            )
+            .await
+    }
+
+    /// Prepare and evaluate a CSV batch, returning its results and whether rates were stale.
+    pub async fn batch(&self, csv: &str) -> (Vec<BatchResultRow>, bool) {
+        let rows = parse_batch_rows("text/csv", csv.as_bytes()).unwrap();
+        let cache = self
+            .taxes_config
+            .prepare_batch(&rows, &self.provider, &self.historical, &self.ppp)
+            .await;
+        let results = rows
+            .iter()
+            .map(|row| self.taxes_config.evaluate_batch_row(&cache, row))
+            .collect();
+        (results, cache.exchange_rates_stale)
+    }
+}
+
+/// `base` with the fields of `patch` set over it, as a request.
+fn patched<T: DeserializeOwned>(mut base: Value, patch: Value) -> T {
+    base.as_object_mut()
+        .unwrap()
+        .extend(patch.as_object().unwrap().clone());
+    serde_json::from_value(base).unwrap()
+}
+
+/// New Zealand and Australia compared up to 200000, at an income of 50000 and with
+/// breakevens, with the fields of `patch` set over it.
+fn nz_au_request(patch: Value) -> TaxPlotDataRequest {
+    patched(
+        json!({
+            "countries": ["New Zealand", "Australia"],
+            "income": 50000.0,
+            "max_income": 200000.0,
+            "show_break_even": true,
+        }),
+        patch,
+    )
+}
@@ -0,0 +1,505 @@
+use crate::controller::taxes_config::{EffectiveExchangeRate, TaxesConfig};
+use crate::core::schedules::amount_schedule::IncomeTaxAmountSchedule;
+use crate::errors::TaxError;
+use crate::exchange_rates::historical::HistoricalExchangeRates;
+use crate::utils::adjust_exchange_rate_schedule;
+use indexmap::IndexMap;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rand_distr::StandardNormal;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use utoipa::ToSchema;
+
+/// Upper bound on the number of scenarios, to keep requests cheap.
+pub const MAX_SAMPLES: usize = 2000;
+/// Upper bound on scenarios times incomes summed over countries, i.e. the tax amounts
+/// evaluated for the bands of one request.
+pub const MAX_UNCERTAINTY_EVALUATIONS: usize = 10_000_000;
+/// Incomes whose tax amounts are held for every scenario at once while computing bands.
+const BAND_CHUNK_INCOMES: usize = 1024;
+
+fn default_samples() -> usize {
+    200
+}
+
+fn default_percentiles() -> Vec<f32> {
+    vec![5.0, 50.0, 95.0]
+}
+
+fn default_horizon_days() -> u32 {
+    365
+}
+
+/// Model exchange rates as random variables and report percentile bands.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct ExchangeRateUncertainty {
+    /// Standard deviation of the change in the log exchange rate over `horizon_days`,
+    /// per currency.
+    #[serde(default)]
+    pub volatilities: BTreeMap<String, f32>,
+    /// Estimate the volatility of currencies missing from `volatilities` from this many days
+    /// of historical rates, up to `exchange_rate_date` or the latest historical rates.
+    #[serde(default)]
+    pub historical_window_days: Option<u32>,
+    /// Days ahead the scenarios look, a tax year by default.
+    #[serde(default = "default_horizon_days")]
+    pub horizon_days: u32,
+    /// Number of exchange rate scenarios to sample.
+    #[serde(default = "default_samples")]
+    pub samples: usize,
+    /// Percentiles (0 to 100) to report.
+    #[serde(default = "default_percentiles")]
+    pub percentiles: Vec<f32>,
+    /// Seed for the scenario generator, so identical requests give identical bands.
+    #[serde(default)]
+    pub seed: u64,
+}
+
+/// Percentile bands of a country's tax curve across exchange rate scenarios.
+#[derive(Debug, PartialEq, Serialize, ToSchema)]
+pub struct UncertaintyBands {
+    pub percentiles: Vec<f32>,
+    /// `tax_amounts[p][i]` is percentile `percentiles[p]` of the tax amount at `incomes[i]`.
+    pub tax_amounts: Vec<Vec<f32>>,
+    pub effective_tax_rates: Vec<Vec<f32>>,
+}
+
+/// Percentile bands of the breakeven incomes of two countries across exchange rate scenarios.
+#[derive(Debug, PartialEq, Serialize, ToSchema)]
+pub struct BreakevenUncertaintyBands {
+    pub percentiles: Vec<f32>,
+    /// `breakeven_incomes[p][k]` is percentile `percentiles[p]` of the breakeven income
+    /// matching the k-th point estimate breakeven, over the scenarios where it is found.
+    pub breakeven_incomes: Vec<Vec<f32>>,
+    /// Number of scenarios where the k-th breakeven is found.
+    pub samples_with_breakeven: Vec<usize>,
+}
+
+/// Linearly interpolated percentile of sorted values.
+pub fn percentile(sorted_values: &[f32], percentile: f32) -> f32 {
+    if sorted_values.is_empty() {
+        return f32::NAN;
+    }
+    let rank = (percentile / 100.0) * (sorted_values.len() - 1) as f32;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    sorted_values[lower] + (sorted_values[upper] - sorted_values[lower]) * (rank - lower as f32)
+}
+
+fn percentiles_of(mut values: Vec<f32>, percentiles: &[f32]) -> Vec<f32> {
+    values.sort_by(f32::total_cmp);
+    percentiles
+        .iter()
+        .map(|&p| percentile(&values, p))
+        .collect()
+}
+
+/// Transpose per-income percentiles into per-percentile curves.
+fn transpose(columns: Vec<Vec<f32>>, rows: usize) -> Vec<Vec<f32>> {
+    (0..rows)
+        .map(|row| columns.iter().map(|column| column[row]).collect())
+        .collect()
+}
+
+/// Sample exchange rate scenarios around the effective rates.
+/// Each currency's log rate gets an independent normal shock, scaled so the expected rate is
+/// unchanged, and countries sharing a currency move together.
+/// Returns the sampled rates per country.
+pub fn sample_exchange_rates(
+    exchange_rates: &IndexMap<String, EffectiveExchangeRate>,
+    country_currencies: &HashMap<String, String>,
+    volatilities: &BTreeMap<String, f32>,
+    samples: usize,
+    seed: u64,
+) -> HashMap<String, Vec<f32>> {
+    // Draw in a fixed order so scenarios only depend on the seed.
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut shocks: BTreeMap<&str, Vec<f32>> = volatilities
+        .keys()
+        .map(|currency| (currency.as_str(), Vec::with_capacity(samples)))
+        .collect();
+    for _ in 0..samples {
+        for (currency, volatility) in volatilities {
+            let z: f32 = rng.sample(StandardNormal);
+            shocks
+                .get_mut(currency.as_str())
+                .unwrap()
+                .push((volatility * z - volatility * volatility / 2.0).exp());
+        }
+    }
+    exchange_rates
+        .iter()
+        .map(|(country, exchange_rate)| {
+            let rates = match shocks.get(country_currencies[country].as_str()) {
+                Some(shocks) => shocks
+                    .iter()
+                    .map(|shock| exchange_rate.rate * shock)
+                    .collect(),
+                None => vec![exchange_rate.rate; samples],
+            };
+            (country.clone(), rates)
+        })
+        .collect()
+}
+
+/// Reject bands that would evaluate more than `MAX_UNCERTAINTY_EVALUATIONS` tax amounts,
+/// given each country's number of incomes.
+pub fn validate_uncertainty_evaluations(
+    samples: usize,
+    income_counts: impl Iterator<Item = usize>,
+) -> Result<(), TaxError> {
+    let incomes: usize = income_counts.sum();
+    if samples.saturating_mul(incomes) > MAX_UNCERTAINTY_EVALUATIONS {
+        return Err(TaxError::invalid_request(
+            "exchange_rate_uncertainty.samples",
+            format!(
+                "{} samples of {} incomes is more than the {} tax amounts allowed",
+                samples, incomes, MAX_UNCERTAINTY_EVALUATIONS
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Percentile bands of tax amounts and effective rates over scenario schedules.
+/// Incomes are taken a chunk at a time, so only that chunk's tax amounts under every scenario
+/// are held at once rather than every scenario's whole curve.
+pub fn tax_uncertainty_bands(
+    schedules: &[IncomeTaxAmountSchedule],
+    incomes: &[f32],
+    percentiles: &[f32],
+) -> Result<UncertaintyBands, TaxError> {
+    let tax_amount_columns: Vec<Vec<f32>> = incomes
+        .par_chunks(BAND_CHUNK_INCOMES)
+        .map(|chunk| {
+            // Same interpolation as the point estimate, so bands with no spread match it exactly.
+            let tax_amounts_by_sample: Vec<Vec<f32>> = schedules
+                .iter()
+                .map(|schedule| schedule.compute_income_taxes(chunk))
+                .collect::<Result<_, TaxError>>()?;
+            Ok((0..chunk.len())
+                .map(|i| {
+                    percentiles_of(
+                        tax_amounts_by_sample
+                            .iter()
+                            .map(|tax_amounts| tax_amounts[i])
+                            .collect(),
+                        percentiles,
+                    )
+                })
+                .collect::<Vec<_>>())
+        })
+        .collect::<Result<Vec<_>, TaxError>>()?
+        .into_iter()
+        .flatten()
+        .collect();
+    // At a fixed income the effective rate is monotone in the tax amount,
+    // so its percentiles follow from the tax amount percentiles.
+    let effective_tax_rate_columns = incomes
+        .iter()
+        .zip(tax_amount_columns.iter())
+        .map(|(&income, column)| {
+            column
+                .iter()
+                .map(|&tax_amount| {
+                    if income == 0.0 {
+                        0.0
+                    } else {
+                        tax_amount / income
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    Ok(UncertaintyBands {
+        percentiles: percentiles.to_vec(),
+        tax_amounts: transpose(tax_amount_columns, percentiles.len()),
+        effective_tax_rates: transpose(effective_tax_rate_columns, percentiles.len()),
+    })
+}
+
+/// Percentile bands of the breakeven incomes found in each scenario.
+/// Scenarios can gain or lose crossings, so each scenario breakeven is matched to the nearest
+/// point estimate breakeven, keeping the closest one when several match. Scenario breakevens
+/// are thus only reported around the point estimate's breakevens.
+pub fn breakeven_uncertainty_bands(
+    breakeven_incomes: &[f32],
+    breakeven_incomes_by_sample: &[Vec<f32>],
+    percentiles: &[f32],
+) -> BreakevenUncertaintyBands {
+    let mut breakeven_columns: Vec<Vec<f32>> = vec![Vec::new(); breakeven_incomes.len()];
+    let distance = |k: usize, income: f32| (income - breakeven_incomes[k]).abs();
+    for sample_incomes in breakeven_incomes_by_sample {
+        let mut matched: Vec<Option<f32>> = vec![None; breakeven_incomes.len()];
+        for &income in sample_incomes {
+            let Some(k) = (0..breakeven_incomes.len())
+                .min_by(|&a, &b| distance(a, income).total_cmp(&distance(b, income)))
+            else {
+                break;
+            };
+            if matched[k].is_none_or(|closest| distance(k, income) < distance(k, closest)) {
+                matched[k] = Some(income);
+            }
+        }
+        for (column, income) in breakeven_columns.iter_mut().zip(matched) {
+            column.extend(income);
+        }
+    }
+    BreakevenUncertaintyBands {
+        percentiles: percentiles.to_vec(),
+        samples_with_breakeven: breakeven_columns
+            .iter()
+            .map(|column| column.len())
+            .collect(),
+        breakeven_incomes: transpose(
+            breakeven_columns
+                .into_iter()
+                .map(|column| percentiles_of(column, percentiles))
+                .collect(),
+            percentiles.len(),
+        ),
+    }
+}
+
+impl TaxesConfig {
+    /// Validate the uncertainty options and sample exchange rate scenarios for every country.
+    pub fn sample_exchange_rate_scenarios(
+        &self,
+        uncertainty: &ExchangeRateUncertainty,
+        currency: &Option<String>,
+        exchange_rate_date: Option<chrono::NaiveDate>,
+        exchange_rates: &IndexMap<String, EffectiveExchangeRate>,
+        historical_exchange_rates: &HistoricalExchangeRates,
+    ) -> Result<HashMap<String, Vec<f32>>, TaxError> {
+        let Some(currency) = currency else {
+            return Err(TaxError::unprocessable_request(
+                "exchange_rate_uncertainty",
+                "requires a normalizing_currency",
+            ));
+        };
+        if uncertainty.samples == 0 || uncertainty.samples > MAX_SAMPLES {
+            return Err(TaxError::invalid_request(
+                "exchange_rate_uncertainty.samples",
+                format!("must be between 1 and {}", MAX_SAMPLES),
+            ));
+        }
+        if let Some(p) = uncertainty
+            .percentiles
+            .iter()
+            .find(|p| !(0.0..=100.0).contains(*p))
+        {
+            return Err(TaxError::invalid_request(
+                "exchange_rate_uncertainty.percentiles",
+                format!("{} must be between 0 and 100", p),
+            ));
+        }
+        if let Some((currency, volatility)) = uncertainty
+            .volatilities
+            .iter()
+            .find(|(_, volatility)| !(volatility.is_finite() && **volatility >= 0.0))
+        {
+            return Err(TaxError::invalid_request(
+                "exchange_rate_uncertainty.volatilities",
+                format!("{} must be non-negative, got {}", currency, volatility),
+            ));
+        }
+        let country_currencies: HashMap<String, String> = exchange_rates
+            .keys()
+            .map(|country| {
+                let currency = self
+                    .get_currency(country)
+                    .ok_or_else(|| TaxError::UnknownCountry(country.clone()))?;
+                Ok((country.clone(), currency.to_string()))
+            })
+            .collect::<Result<_, TaxError>>()?;
+        let mut volatilities = BTreeMap::new();
+        for country_currency in country_currencies.values() {
+            let volatility = match (
+                uncertainty.volatilities.get(country_currency),
+                uncertainty.historical_window_days,
+            ) {
+                (Some(&volatility), _) => volatility,
+                // The normalizing currency is fixed against itself.
+                (None, _) if country_currency == currency => continue,
+                (None, Some(window_days)) => {
+                    let end = exchange_rate_date
+                        .or(historical_exchange_rates.latest_date())
+                        .ok_or_else(|| {
+                            TaxError::unprocessable_request(
+                                "exchange_rate_uncertainty.historical_window_days",
+                                "no historical exchange rates are loaded",
+                            )
+                        })?;
+                    historical_exchange_rates.log_rate_volatility(
+                        end,
+                        window_days,
+                        uncertainty.horizon_days,
+                        currency,
+                        country_currency,
+                    )?
+                }
+                (None, None) => continue,
+            };
+            volatilities.insert(country_currency.clone(), volatility);
+        }
+        Ok(sample_exchange_rates(
+            exchange_rates,
+            &country_currencies,
+            &volatilities,
+            uncertainty.samples,
+            uncertainty.seed,
+        ))
+    }
+
+    /// Tax schedule of a country under each sampled exchange rate.
+    pub fn scenario_schedules(
+        &self,
+        country: &str,
+        scenario_rates: &[f32],
+        max_income_to_consider: f32,
+    ) -> Result<Vec<IncomeTaxAmountSchedule>, TaxError> {
+        scenario_rates
+            .par_iter()
+            .map(|&rate| {
+                adjust_exchange_rate_schedule(self, country, &Some(rate), max_income_to_consider)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::controller::taxes_config::{EffectiveExchangeRate, ExchangeRateSource};
+    use crate::controller::uncertainty::{
+        breakeven_uncertainty_bands, percentile, sample_exchange_rates, tax_uncertainty_bands,
+    };
+    use crate::core::points::tax_amount::IncomeTaxKnot;
+    use crate::core::schedules::amount_schedule::IncomeTaxAmountSchedule;
+    use crate::errors::TaxError;
+    use indexmap::IndexMap;
+    use std::collections::{BTreeMap, HashMap};
+
+    #[test]
+    fn test_percentile() {
+        let values = vec![1.0, 2.0, 3.0, 4.0, 5.0];
+        assert_eq!(percentile(&values, 0.0), 1.0);
+        assert_eq!(percentile(&values, 50.0), 3.0);
+        assert_eq!(percentile(&values, 100.0), 5.0);
+        assert_eq!(percentile(&values, 12.5), 1.5);
+        assert!(percentile(&[], 50.0).is_nan());
+    }
+
+    #[test]
+    fn test_sample_exchange_rates() {
+        let rate = |rate| EffectiveExchangeRate {
+            rate,
+            source: ExchangeRateSource::Latest,
+        };
+        let exchange_rates = IndexMap::from([
+            ("New Zealand".to_string(), rate(1.0)),
+            ("Australia".to_string(), rate(0.9)),
+            ("Spain".to_string(), rate(0.5)),
+            ("Ireland".to_string(), rate(0.5)),
+        ]);
+        let country_currencies = HashMap::from([
+            ("New Zealand".to_string(), "NZD".to_string()),
+            ("Australia".to_string(), "AUD".to_string()),
+            ("Spain".to_string(), "EUR".to_string()),
+            ("Ireland".to_string(), "EUR".to_string()),
+        ]);
+        let volatilities = BTreeMap::from([("AUD".to_string(), 0.1), ("EUR".to_string(), 0.2)]);
+        let scenarios =
+            sample_exchange_rates(&exchange_rates, &country_currencies, &volatilities, 1000, 7);
+
+        assert_eq!(scenarios["New Zealand"], vec![1.0; 1000]);
+        assert_eq!(scenarios["Spain"], scenarios["Ireland"]);
+        assert_ne!(scenarios["Spain"], scenarios["Australia"]);
+        let mean = scenarios["Australia"].iter().sum::<f32>() / 1000.0;
+        assert!((mean - 0.9).abs() < 0.01);
+
+        // Same seed, same scenarios.
+        assert_eq!(
+            scenarios,
+            sample_exchange_rates(&exchange_rates, &country_currencies, &volatilities, 1000, 7)
+        );
+    }
+
+    #[test]
+    fn test_tax_uncertainty_bands() {
+        // A flat 10% tax at three exchange rates: thresholds don't matter, amounts don't move.
+        let flat = IncomeTaxAmountSchedule::new(vec![
+            IncomeTaxKnot::new(0.0, 0.0),
+            IncomeTaxKnot::new(100.0, 10.0),
+        ]);
+        let steeper = IncomeTaxAmountSchedule::new(vec![
+            IncomeTaxKnot::new(0.0, 0.0),
+            IncomeTaxKnot::new(100.0, 30.0),
+        ]);
+        let bands = tax_uncertainty_bands(
+            &[flat.clone(), steeper.clone(), flat.clone()],
+            &[0.0, 50.0, 100.0],
+            &[0.0, 50.0, 100.0],
+        )
+        .unwrap();
+        assert_eq!(
+            bands.tax_amounts,
+            vec![
+                vec![0.0, 5.0, 10.0],
+                vec![0.0, 5.0, 10.0],
+                vec![0.0, 15.0, 30.0]
+            ]
+        );
+        assert_eq!(
+            bands.effective_tax_rates,
+            vec![
+                vec![0.0, 0.1, 0.1],
+                vec![0.0, 0.1, 0.1],
+                vec![0.0, 0.3, 0.3]
+            ]
+        );
+
+        // Bands over many chunks of incomes line up with the incomes.
+        let incomes: Vec<f32> = (0..=2500).map(|i| i as f32 / 25.0).collect();
+        let bands =
+            tax_uncertainty_bands(&[flat.clone(), steeper], &incomes, &[0.0, 100.0]).unwrap();
+        assert_eq!(
+            bands.tax_amounts[0],
+            flat.compute_income_taxes(&incomes).unwrap()
+        );
+        assert_eq!(bands.tax_amounts[0].len(), incomes.len());
+
+        // Incomes beyond a scenario's schedule are an error, not a NaN percentile.
+        assert_eq!(
+            tax_uncertainty_bands(&[flat], &[200.0], &[50.0]),
+            Err(TaxError::IncomeOutOfBounds {
+                income: 200.0,
+                bounds: (200.0, 100.0)
+            })
+        );
+    }
+
+    #[test]
+    fn test_breakeven_uncertainty_bands() {
+        // The second scenario loses the lower crossing, so its only crossing is matched to the
+        // upper one rather than counted with the lower ones. The extra crossing at 400 in the
+        // last scenario has no point estimate counterpart and is dropped.
+        let bands = breakeven_uncertainty_bands(
+            &[20.0, 150.0],
+            &[
+                vec![10.0, 100.0],
+                vec![160.0],
+                vec![30.0, 200.0],
+                vec![],
+                vec![25.0, 140.0, 400.0],
+            ],
+            &[0.0, 100.0],
+        );
+        assert_eq!(bands.samples_with_breakeven, vec![3, 4]);
+        assert_eq!(
+            bands.breakeven_incomes,
+            vec![vec![10.0, 100.0], vec![30.0, 200.0]]
+        );
+    }
+}
@@ -0,0 +1,159 @@
+use crate::core::schedules::marginal_schedule::MarginalIncomeTaxRateSchedule;
+use crate::errors::TaxError;
+
+/// Stop bisecting once the bracket is this tight, relative to the rate.
+const TIE_RATE_TOLERANCE: f64 = 1e-6;
+/// How many times to double the search bracket before giving up.
+const MAX_BRACKET_DOUBLINGS: u32 = 64;
+const MAX_BISECTIONS: u32 = 100;
+
+/// Tax on `income` (in the normalizing currency) under `schedule` at `exchange_rate`.
+fn normalized_tax_amount(
+    schedule: &MarginalIncomeTaxRateSchedule,
+    exchange_rate: f64,
+    income: f32,
+) -> Result<f32, TaxError> {
+    Ok(schedule
+        .exchange_rate_adjustment(&Some(exchange_rate as f32))
+        .to_income_amount_schedule(income)
+        .compute_income_taxes(&[income])?[0])
+}
+
+/// Find the exchange rate of country B at which it taxes `income` the same as country A,
+/// holding A's exchange rate fixed. Rates are in units of local currency per unit of the
+/// normalizing currency, as in `exchange_rate_adjustment`.
+/// Net incomes tie at the same rate, since both are `income` less the tax amount.
+///
+/// Assumes B's tax on a normalized income never falls as its exchange rate grows, which
+/// holds when B's average rate does not fall with income. The rate is bisected (on a log
+/// scale) from a bracket grown outwards from `exchange_rate_b`. If B's tax moves away from
+/// A's while the bracket grows, the assumption is broken and `NoTieExchangeRate` is
+/// returned, as it is when the bracket never changes sign.
+pub fn solve_tie_exchange_rate(
+    schedule_a: &MarginalIncomeTaxRateSchedule,
+    exchange_rate_a: f32,
+    schedule_b: &MarginalIncomeTaxRateSchedule,
+    exchange_rate_b: f32,
+    income: f32,
+) -> Result<f32, TaxError> {
+    if income < 0.0 {
+        return Err(TaxError::NegativeIncome(income));
+    }
+    if income == 0.0 {
+        // Nothing is taxed, so every rate ties.
+        return Ok(exchange_rate_b);
+    }
+    let target = normalized_tax_amount(schedule_a, exchange_rate_a as f64, income)?;
+    let excess = |log_rate: f64| -> Result<f32, TaxError> {
+        Ok(normalized_tax_amount(schedule_b, log_rate.exp(), income)? - target)
+    };
+
+    let start = (exchange_rate_b as f64).ln();
+    let start_excess = excess(start)?;
+    if start_excess == 0.0 {
+        return Ok(exchange_rate_b);
+    }
+    // B taxes too little: a weaker currency moves the income into higher brackets.
+    let direction = if start_excess < 0.0 { 1.0 } else { -1.0 };
+    let mut near = start;
+    let mut near_excess = start_excess;
+    let mut far = start;
+    let mut doublings = 0;
+    loop {
+        far += direction * std::f64::consts::LN_2;
+        let far_excess = excess(far)?;
+        if far_excess == 0.0 {
+            return Ok(far.exp() as f32);
+        }
+        if (far_excess > 0.0) == (start_excess < 0.0) {
+            break;
+        }
+        if far_excess.abs() > near_excess.abs() {
+            // B's tax is not monotonic in its exchange rate.
+            return Err(TaxError::NoTieExchangeRate { income });
+        }
+        near = far;
+        near_excess = far_excess;
+        doublings += 1;
+        if doublings == MAX_BRACKET_DOUBLINGS {
+            return Err(TaxError::NoTieExchangeRate { income });
+        }
+    }
+
+    for _ in 0..MAX_BISECTIONS {
+        if (far - near).abs() < TIE_RATE_TOLERANCE {
+            break;
+        }
+        let mid = (near + far) / 2.0;
+        let mid_excess = excess(mid)?;
+        if mid_excess == 0.0 {
+            return Ok(mid.exp() as f32);
+        }
+        if (mid_excess < 0.0) == (start_excess < 0.0) {
+            near = mid;
+        } else {
+            far = mid;
+        }
+    }
+    Ok(((near + far) / 2.0).exp() as f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::exchange_rate_tie::solve_tie_exchange_rate;
+    use crate::core::points::marginal_rate_knot::MarginalRateKnot;
+    use crate::core::schedules::marginal_schedule::MarginalIncomeTaxRateSchedule;
+    use crate::errors::TaxError;
+
+    fn flat(rate: f32) -> MarginalIncomeTaxRateSchedule {
+        MarginalIncomeTaxRateSchedule::new(vec![MarginalRateKnot::new(Some(f32::INFINITY), rate)])
+    }
+
+    fn progressive() -> MarginalIncomeTaxRateSchedule {
+        MarginalIncomeTaxRateSchedule::new(vec![
+            MarginalRateKnot::new(Some(10000.0), 0.0),
+            MarginalRateKnot::new(Some(f32::INFINITY), 0.5),
+        ])
+    }
+
+    #[test]
+    fn test_solve_tie_exchange_rate() {
+        // A takes 30% of 20000, i.e. 6000. B takes half of the income over 10000 of its
+        // currency, so 0.5 * (20000 r - 10000) / r = 6000 at r = 1.25.
+        let rate = solve_tie_exchange_rate(&flat(0.3), 1.0, &progressive(), 1.0, 20000.0).unwrap();
+        assert!((rate / 1.25 - 1.0).abs() < 1e-6);
+        // The same rate is found from the other side.
+        let rate = solve_tie_exchange_rate(&flat(0.3), 1.0, &progressive(), 20.0, 20000.0).unwrap();
+        assert!((rate / 1.25 - 1.0).abs() < 1e-6);
+        // A's own exchange rate does not matter to a flat tax.
+        let rate = solve_tie_exchange_rate(&flat(0.3), 3.0, &progressive(), 1.0, 20000.0).unwrap();
+        assert!((rate / 1.25 - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_solve_tie_exchange_rate_without_tie() {
+        // A flat tax cannot be moved by the exchange rate.
+        assert_eq!(
+            solve_tie_exchange_rate(&progressive(), 1.0, &flat(0.2), 1.0, 50000.0),
+            Err(TaxError::NoTieExchangeRate { income: 50000.0 })
+        );
+        // B never takes more than half.
+        assert_eq!(
+            solve_tie_exchange_rate(&flat(0.6), 1.0, &progressive(), 1.0, 50000.0),
+            Err(TaxError::NoTieExchangeRate { income: 50000.0 })
+        );
+        // A regressive B taxes less as its currency weakens, so it is never searched.
+        let regressive = MarginalIncomeTaxRateSchedule::new(vec![
+            MarginalRateKnot::new(Some(10000.0), 0.5),
+            MarginalRateKnot::new(Some(f32::INFINITY), 0.0),
+        ]);
+        assert_eq!(
+            solve_tie_exchange_rate(&flat(0.4), 1.0, &regressive, 1.0, 20000.0),
+            Err(TaxError::NoTieExchangeRate { income: 20000.0 })
+        );
+        assert_eq!(
+            solve_tie_exchange_rate(&flat(0.2), 1.0, &progressive(), 1.0, -1.0),
+            Err(TaxError::NegativeIncome(-1.0))
+        );
+    }
+}
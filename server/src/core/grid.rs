@@ -0,0 +1,260 @@
+use crate::core::sampling::adaptive_incomes;
+use crate::core::schedules::amount_schedule::IncomeTaxAmountSchedule;
+use crate::errors::TaxError;
+
+/// Relative slack when deciding whether the last grid point lands on `max_income`,
+/// so rounding in `min_income + i * step` neither drops nor duplicates it.
+const GRID_END_TOLERANCE: f64 = 1e-9;
+
+/// Incomes at which a tax curve is evaluated.
+/// Build linear and log grids with [`IncomeGrid::linear`] and [`IncomeGrid::log`],
+/// which reject bounds and steps that would never reach `max_income`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum IncomeGrid {
+    /// Sampled from the schedule's curvature, see [`adaptive_incomes`].
+    Adaptive { min_income: f32, max_error: f32 },
+    /// `min_income + i * step` up to `max_income`.
+    Linear {
+        min_income: f32,
+        max_income: f32,
+        step: f32,
+    },
+    /// `min_income * 10^(i * step)` up to `max_income`, i.e. `step` in decades.
+    Log {
+        min_income: f32,
+        max_income: f32,
+        step: f32,
+    },
+    /// Exactly these incomes, sorted ascending.
+    Explicit(Vec<f32>),
+}
+
+impl IncomeGrid {
+    /// `min_income + i * step` up to `max_income`.
+    pub fn linear(min_income: f32, max_income: f32, step: f32) -> Result<Self, TaxError> {
+        Self::validate(min_income, max_income, step)?;
+        Ok(IncomeGrid::Linear {
+            min_income,
+            max_income,
+            step,
+        })
+    }
+
+    /// `min_income * 10^(i * step)` up to `max_income`, with a positive `min_income`.
+    pub fn log(min_income: f32, max_income: f32, step: f32) -> Result<Self, TaxError> {
+        Self::validate(min_income, max_income, step)?;
+        if min_income <= 0.0 {
+            return Err(TaxError::invalid_request(
+                "min_income",
+                "must be positive for a log grid",
+            ));
+        }
+        Ok(IncomeGrid::Log {
+            min_income,
+            max_income,
+            step,
+        })
+    }
+
+    fn validate(min_income: f32, max_income: f32, step: f32) -> Result<(), TaxError> {
+        if !(step.is_finite() && step > 0.0) {
+            return Err(TaxError::invalid_request(
+                "step",
+                format!("must be positive, got {}", step),
+            ));
+        }
+        if !max_income.is_finite() {
+            return Err(TaxError::invalid_request(
+                "max_income",
+                format!("must be finite, got {}", max_income),
+            ));
+        }
+        if !(min_income.is_finite() && min_income <= max_income) {
+            return Err(TaxError::invalid_request(
+                "min_income",
+                format!("must not be above max_income, got {}", min_income),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Sorted incomes of the grid. Linear and log grids always end at `max_income`.
+    pub fn incomes(&self, schedule: &IncomeTaxAmountSchedule) -> Vec<f32> {
+        match self {
+            IncomeGrid::Adaptive {
+                min_income,
+                max_error,
+            } => {
+                let incomes = adaptive_incomes(schedule, *max_error);
+                let start = incomes.partition_point(|income| income < min_income);
+                let mut grid = Vec::with_capacity(incomes.len() - start + 1);
+                if incomes.get(start) != Some(min_income) {
+                    grid.push(*min_income);
+                }
+                grid.extend_from_slice(&incomes[start..]);
+                grid
+            }
+            IncomeGrid::Linear {
+                min_income,
+                max_income,
+                step,
+            } => {
+                let (min_income, step) = (*min_income as f64, *step as f64);
+                with_end(
+                    (0..=Self::steps(min_income, *max_income as f64, step))
+                        .map(|i| (min_income + i as f64 * step) as f32)
+                        .collect(),
+                    *max_income,
+                )
+            }
+            IncomeGrid::Log {
+                min_income,
+                max_income,
+                step,
+            } => {
+                let (log_min, step) = ((*min_income as f64).log10(), *step as f64);
+                with_end(
+                    (0..=Self::steps(log_min, (*max_income as f64).log10(), step))
+                        .map(|i| 10f64.powf(log_min + i as f64 * step) as f32)
+                        .collect(),
+                    *max_income,
+                )
+            }
+            IncomeGrid::Explicit(incomes) => incomes.clone(),
+        }
+    }
+
+    /// Number of whole steps from `start` to `stop`.
+    /// Each point is computed from its index rather than by accumulating `step`,
+    /// so there is no drift over long grids.
+    fn steps(start: f64, stop: f64, step: f64) -> usize {
+        ((stop - start) / step * (1.0 + GRID_END_TOLERANCE)).floor() as usize
+    }
+
+    /// Number of incomes a linear or log grid would produce, to bound requests up front.
+    pub fn len_hint(&self) -> Option<usize> {
+        match self {
+            IncomeGrid::Linear {
+                min_income,
+                max_income,
+                step,
+            } => Some(Self::steps(*min_income as f64, *max_income as f64, *step as f64) + 2),
+            IncomeGrid::Log {
+                min_income,
+                max_income,
+                step,
+            } => Some(
+                Self::steps(
+                    (*min_income as f64).log10(),
+                    (*max_income as f64).log10(),
+                    *step as f64,
+                ) + 2,
+            ),
+            IncomeGrid::Adaptive { .. } | IncomeGrid::Explicit(_) => None,
+        }
+    }
+}
+
+/// Replace a last point that rounded to within tolerance of `max_income` with it exactly,
+/// or append `max_income` when the grid stops short of it.
+fn with_end(mut incomes: Vec<f32>, max_income: f32) -> Vec<f32> {
+    let close = |income: f32| {
+        ((max_income - income) as f64).abs() <= GRID_END_TOLERANCE * (max_income as f64).abs()
+    };
+    match incomes.last() {
+        Some(&last) if close(last) => *incomes.last_mut().unwrap() = max_income,
+        _ => incomes.push(max_income),
+    }
+    incomes
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::grid::IncomeGrid;
+    use crate::core::points::tax_amount::IncomeTaxKnot;
+    use crate::core::schedules::amount_schedule::IncomeTaxAmountSchedule;
+    use crate::errors::TaxError;
+
+    fn schedule() -> IncomeTaxAmountSchedule {
+        IncomeTaxAmountSchedule::new(vec![
+            IncomeTaxKnot::new(0.0, 0.0),
+            IncomeTaxKnot::new(10000.0, 1000.0),
+            IncomeTaxKnot::new(20000000.0, 5998000.0),
+        ])
+    }
+
+    #[test]
+    fn test_linear_grid_has_no_drift() {
+        let grid = IncomeGrid::Linear {
+            min_income: 0.0,
+            max_income: 100000.0,
+            step: 0.1,
+        };
+        let incomes = grid.incomes(&schedule());
+        // Accumulating 0.1 in f32 drifts by hundreds over a million additions.
+        assert_eq!(incomes.len(), 1000001);
+        assert!((incomes[123456] - 12345.6).abs() < 0.01);
+        assert_eq!(incomes.last(), Some(&100000.0));
+
+        let incomes = IncomeGrid::Linear {
+            min_income: 100.0,
+            max_income: 125.0,
+            step: 10.0,
+        }
+        .incomes(&schedule());
+        assert_eq!(incomes, vec![100.0, 110.0, 120.0, 125.0]);
+    }
+
+    #[test]
+    fn test_grid_rejects_bad_steps() {
+        for step in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            assert!(matches!(
+                IncomeGrid::linear(0.0, 100.0, step),
+                Err(TaxError::InvalidRequest { field, .. }) if field == "step"
+            ));
+        }
+        assert!(IncomeGrid::linear(0.0, f32::INFINITY, 1.0).is_err());
+        assert!(IncomeGrid::linear(200.0, 100.0, 1.0).is_err());
+        assert!(matches!(
+            IncomeGrid::log(0.0, 100.0, 0.1),
+            Err(TaxError::InvalidRequest { field, .. }) if field == "min_income"
+        ));
+        assert!(IncomeGrid::log(1.0, 100.0, 0.1).is_ok());
+    }
+
+    #[test]
+    fn test_log_grid() {
+        let grid = IncomeGrid::Log {
+            min_income: 10000.0,
+            max_income: 10000000.0,
+            step: 0.5,
+        };
+        assert_eq!(grid.len_hint(), Some(8));
+        let incomes = grid.incomes(&schedule());
+        let expected = [
+            1e4,
+            10f32.powf(4.5),
+            1e5,
+            10f32.powf(5.5),
+            1e6,
+            10f32.powf(6.5),
+            1e7,
+        ];
+        assert_eq!(incomes.len(), expected.len());
+        for (income, expected) in incomes.iter().zip(expected) {
+            assert!((income / expected - 1.0).abs() < 1e-6, "{}", income);
+        }
+    }
+
+    #[test]
+    fn test_adaptive_grid_from_min_income() {
+        let incomes = IncomeGrid::Adaptive {
+            min_income: 15000.0,
+            max_error: 1e-4,
+        }
+        .incomes(&schedule());
+        assert_eq!(incomes[0], 15000.0);
+        assert!(incomes[1] > 15000.0);
+        assert_eq!(incomes.last(), Some(&20000000.0));
+    }
+}
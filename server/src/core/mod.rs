@@ -1,3 +1,7 @@
+pub mod exchange_rate_tie;
+pub mod grid;
+pub mod pay_periods;
 pub mod points;
+pub mod sampling;
 pub mod schedules;
 pub mod segment;
@@ -0,0 +1,144 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// How often pay, and the tax withheld from it, is received.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PayPeriod {
+    Annual,
+    Monthly,
+    Fortnightly,
+    Weekly,
+}
+
+impl PayPeriod {
+    pub const ALL: [PayPeriod; 4] = [
+        PayPeriod::Annual,
+        PayPeriod::Monthly,
+        PayPeriod::Fortnightly,
+        PayPeriod::Weekly,
+    ];
+
+    pub fn periods_per_year(self) -> f32 {
+        match self {
+            PayPeriod::Annual => 1.0,
+            PayPeriod::Monthly => 12.0,
+            PayPeriod::Fortnightly => 26.0,
+            PayPeriod::Weekly => 52.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RoundingMode {
+    #[default]
+    Nearest,
+    Down,
+    Up,
+}
+
+/// How the tax withheld each pay period is rounded, in the country's own currency.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct WithholdingRounding {
+    /// Amounts are rounded to a multiple of this, e.g. `1` for whole units of currency.
+    pub increment: f32,
+    #[serde(default)]
+    pub mode: RoundingMode,
+}
+
+impl WithholdingRounding {
+    pub fn round(&self, amount: f32) -> f32 {
+        let (amount, increment) = (amount as f64, self.increment as f64);
+        let multiples = amount / increment;
+        let multiples = match self.mode {
+            RoundingMode::Nearest => multiples.round(),
+            RoundingMode::Down => multiples.floor(),
+            RoundingMode::Up => multiples.ceil(),
+        };
+        (multiples * increment) as f32
+    }
+}
+
+/// Pay and tax over one pay period.
+#[derive(Clone, Debug, PartialEq, Serialize, ToSchema)]
+pub struct PayPeriodAmounts {
+    pub period: PayPeriod,
+    pub gross_income: f32,
+    pub tax_amount: f32,
+    pub net_income: f32,
+}
+
+/// Split an annual income and its tax over every pay period.
+/// Annual amounts are exact. The tax withheld in shorter periods is rounded by `rounding`,
+/// which applies to amounts in the country's own currency, i.e. after multiplying by
+/// `exchange_rate`.
+pub fn pay_period_breakdown(
+    income: f32,
+    tax_amount: f32,
+    rounding: Option<&WithholdingRounding>,
+    exchange_rate: f32,
+) -> Vec<PayPeriodAmounts> {
+    PayPeriod::ALL
+        .into_iter()
+        .map(|period| {
+            let gross_income = income / period.periods_per_year();
+            let tax_amount = tax_amount / period.periods_per_year();
+            let tax_amount = match rounding {
+                Some(rounding) if period != PayPeriod::Annual => {
+                    rounding.round(tax_amount * exchange_rate) / exchange_rate
+                }
+                _ => tax_amount,
+            };
+            PayPeriodAmounts {
+                period,
+                gross_income,
+                tax_amount,
+                net_income: gross_income - tax_amount,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::pay_periods::{
+        pay_period_breakdown, PayPeriod, RoundingMode, WithholdingRounding,
+    };
+
+    #[test]
+    fn test_withholding_rounding() {
+        let rounding = |increment, mode| WithholdingRounding { increment, mode };
+        assert_eq!(rounding(1.0, RoundingMode::Nearest).round(12.5), 13.0);
+        assert_eq!(rounding(1.0, RoundingMode::Down).round(12.9), 12.0);
+        assert_eq!(rounding(1.0, RoundingMode::Up).round(12.1), 13.0);
+        assert_eq!(rounding(0.05, RoundingMode::Nearest).round(1.02), 1.0);
+        assert_eq!(rounding(10.0, RoundingMode::Nearest).round(1234.0), 1230.0);
+    }
+
+    #[test]
+    fn test_pay_period_breakdown() {
+        let breakdown = pay_period_breakdown(52000.0, 10400.5, None, 1.0);
+        assert_eq!(
+            breakdown
+                .iter()
+                .map(|amounts| amounts.period)
+                .collect::<Vec<_>>(),
+            PayPeriod::ALL
+        );
+        assert_eq!(breakdown[0].net_income, 52000.0 - 10400.5);
+        assert_eq!(breakdown[3].gross_income, 1000.0);
+        assert!((breakdown[3].tax_amount - 200.00961).abs() < 1e-3);
+
+        // Weekly withholding rounds to whole units of a currency worth half as much.
+        let rounding = WithholdingRounding {
+            increment: 1.0,
+            mode: RoundingMode::Nearest,
+        };
+        let breakdown = pay_period_breakdown(52000.0, 10400.5, Some(&rounding), 2.0);
+        assert_eq!(breakdown[0].tax_amount, 10400.5);
+        assert_eq!(breakdown[3].tax_amount, 200.0);
+        assert_eq!(breakdown[3].net_income, 800.0);
+        assert_eq!(breakdown[1].tax_amount, 866.5);
+    }
+}
@@ -1,8 +1,9 @@
 use serde::{Deserialize, Deserializer, Serialize};
 use serde_json;
+use utoipa::ToSchema;
 
 /// A point characterised by a marginal tax rate at a given level of income
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
 pub struct MarginalRateKnot {
     /// The marginal tax rate f(x) at given income threshold x
     marginal_rate: f32,
@@ -1,7 +1,8 @@
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 /// A point characterised by tax amount at given income, which is also denoted as a knot point
-#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize, ToSchema)]
 pub struct IncomeTaxKnot {
     /// Income tax amount f(x) for a given maximimum income level x
     income_tax_amount: f32,
@@ -0,0 +1,99 @@
+use crate::core::schedules::amount_schedule::IncomeTaxAmountSchedule;
+
+/// Default largest gap between the effective rate curve and straight lines drawn through
+/// the sampled incomes, i.e. 0.01 percentage points.
+pub const DEFAULT_MAX_EFFECTIVE_RATE_ERROR: f32 = 1e-4;
+
+/// Incomes to sample a schedule's effective rate curve at, from 0 to its last knot.
+///
+/// Every knot is sampled. Within a segment the effective rate is `slope + intercept / x`, and
+/// the chord between samples `x0 < x1` strays furthest from it at `sqrt(x0 * x1)`, by
+/// `|intercept| * (1 / sqrt(x0) - 1 / sqrt(x1))^2`. Each step is the longest that keeps this
+/// within `max_error`, so samples are dense just past a knot and thin out as the curve
+/// converges to the top marginal rate.
+pub fn adaptive_incomes(schedule: &IncomeTaxAmountSchedule, max_error: f32) -> Vec<f32> {
+    let mut incomes = vec![0.0];
+    for segment in schedule.effective_rate_segments() {
+        let end = segment.income_end as f64;
+        let mut income = segment.income_start as f64;
+        let intercept = (segment.intercept as f64).abs();
+        if income > 0.0 && intercept > 0.0 {
+            let step = (max_error as f64 / intercept).sqrt();
+            loop {
+                // 1 / sqrt(x1) = 1 / sqrt(x0) - sqrt(max_error / |intercept|)
+                let inverse_sqrt = 1.0 / income.sqrt() - step;
+                if inverse_sqrt <= 0.0 {
+                    break;
+                }
+                income = 1.0 / (inverse_sqrt * inverse_sqrt);
+                if income >= end {
+                    break;
+                }
+                incomes.push(income as f32);
+            }
+        }
+        incomes.push(segment.income_end);
+    }
+    incomes.dedup();
+    incomes
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::points::tax_amount::IncomeTaxKnot;
+    use crate::core::sampling::adaptive_incomes;
+    use crate::core::schedules::amount_schedule::IncomeTaxAmountSchedule;
+    use crate::utils::compute_effective_tax_rates;
+
+    fn schedule() -> IncomeTaxAmountSchedule {
+        IncomeTaxAmountSchedule::new(vec![
+            IncomeTaxKnot::new(0.0, 0.0),
+            IncomeTaxKnot::new(10000.0, 1000.0),
+            IncomeTaxKnot::new(20000.0, 3000.0),
+            IncomeTaxKnot::new(1000000.0, 297000.0),
+        ])
+    }
+
+    #[test]
+    fn test_adaptive_incomes_include_knots() {
+        let incomes = adaptive_incomes(&schedule(), 1e-4);
+        for knot in schedule().schedule() {
+            assert!(incomes.contains(&knot.income_limit()));
+        }
+        assert!(incomes.windows(2).all(|pair| pair[0] < pair[1]));
+        // The first segment has a constant effective rate, so needs no samples in between.
+        assert_eq!(incomes[..2], [0.0, 10000.0]);
+    }
+
+    #[test]
+    fn test_adaptive_incomes_within_max_error() {
+        let schedule = schedule();
+        let max_error = 1e-4;
+        let incomes = adaptive_incomes(&schedule, max_error);
+        // Far fewer points than a step of 10 up to 1e6.
+        assert!(incomes.len() < 500, "{}", incomes.len());
+
+        // Linear interpolation between samples stays close to the exact curve.
+        let tax_amounts = schedule.compute_income_taxes(&incomes).unwrap();
+        let rates = compute_effective_tax_rates(&incomes, &tax_amounts);
+        for i in 1..incomes.len() - 1 {
+            let (x0, x1) = (incomes[i], incomes[i + 1]);
+            let midpoint = (x0 * x1).sqrt();
+            let exact = schedule
+                .compute_specific_income_tax(Some(midpoint))
+                .unwrap()
+                / midpoint;
+            let interpolated = rates[i] + (rates[i + 1] - rates[i]) * (midpoint - x0) / (x1 - x0);
+            assert!(
+                (exact - interpolated).abs() <= max_error * 1.01,
+                "{} between {} and {}",
+                (exact - interpolated).abs(),
+                x0,
+                x1
+            );
+        }
+
+        // A looser tolerance needs fewer samples.
+        assert!(adaptive_incomes(&schedule, 1e-3).len() < incomes.len());
+    }
+}
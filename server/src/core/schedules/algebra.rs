@@ -0,0 +1,280 @@
+use crate::core::points::tax_amount::IncomeTaxKnot;
+use crate::core::schedules::amount_schedule::IncomeTaxAmountSchedule;
+use crate::errors::TaxError;
+
+/// Operations combining tax amount schedules into new exact schedules.
+///
+/// Binary operations are defined over the incomes both schedules cover, i.e. up to the lower
+/// of their last knots. Between the merged knots of two schedules both are linear, so sums
+/// and differences are exact; for maxima and minima the incomes where the two cross are added
+/// as knots too. Arithmetic is done in f64 and rounded once per knot.
+impl IncomeTaxAmountSchedule {
+    /// Pointwise sum, e.g. federal plus state tax.
+    pub fn add(&self, other: &Self) -> Self {
+        self.combine(other, false, |a, b| a + b)
+    }
+
+    /// Pointwise difference, e.g. tax less a credit.
+    pub fn subtract(&self, other: &Self) -> Self {
+        self.combine(other, false, |a, b| a - b)
+    }
+
+    /// Pointwise maximum, e.g. the greater of regular tax and an alternative minimum tax.
+    pub fn pointwise_max(&self, other: &Self) -> Self {
+        self.combine(other, true, f64::max)
+    }
+
+    /// Pointwise minimum, e.g. tax capped by another schedule.
+    pub fn pointwise_min(&self, other: &Self) -> Self {
+        self.combine(other, true, f64::min)
+    }
+
+    /// Tax amounts multiplied by `factor`, e.g. a levy charged as a share of income tax.
+    pub fn scale(&self, factor: f32) -> Self {
+        Self::new(
+            self.schedule()
+                .iter()
+                .map(|knot| {
+                    IncomeTaxKnot::new(
+                        knot.income_limit(),
+                        (knot.income_tax_amount() as f64 * factor as f64) as f32,
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    /// The schedule moved along the income axis, so tax at `income` is the original tax at
+    /// `income - offset`. A positive offset, e.g. a deduction, keeps the tax at zero income
+    /// below `offset`. A negative offset drops incomes below `-offset` and must stay within
+    /// the last knot.
+    pub fn shift(&self, offset: f32) -> Result<Self, TaxError> {
+        let knots = self.schedule();
+        let (Some(first), Some(last)) = (knots.first(), knots.last()) else {
+            return Ok(self.clone());
+        };
+        let offset = offset as f64;
+        if offset >= 0.0 {
+            let mut shifted = Vec::with_capacity(knots.len() + 1);
+            if offset > 0.0 {
+                shifted.push(IncomeTaxKnot::new(0.0, first.income_tax_amount()));
+            }
+            shifted.extend(knots.iter().map(|knot| {
+                IncomeTaxKnot::new(
+                    (knot.income_limit() as f64 + offset) as f32,
+                    knot.income_tax_amount(),
+                )
+            }));
+            return Ok(Self::new(shifted));
+        }
+        let start = -offset;
+        if start >= last.income_limit() as f64 {
+            return Err(TaxError::IncomeOutOfBounds {
+                income: start as f32,
+                bounds: (first.income_limit(), last.income_limit()),
+            });
+        }
+        let mut shifted = vec![IncomeTaxKnot::new(0.0, self.tax_at(start) as f32)];
+        shifted.extend(
+            knots
+                .iter()
+                .filter(|knot| knot.income_limit() as f64 > start)
+                .map(|knot| {
+                    IncomeTaxKnot::new(
+                        (knot.income_limit() as f64 - start) as f32,
+                        knot.income_tax_amount(),
+                    )
+                }),
+        );
+        Ok(Self::new(shifted))
+    }
+
+    /// Evaluate `op` on both schedules at every knot of either, and where they cross when
+    /// `crossings` is set.
+    fn combine(&self, other: &Self, crossings: bool, op: impl Fn(f64, f64) -> f64) -> Self {
+        let (Some(start), Some(end)) = (
+            Self::common_bound(self, other, |knots| knots.first(), f64::max),
+            Self::common_bound(self, other, |knots| knots.last(), f64::min),
+        ) else {
+            return Self::new(Vec::new());
+        };
+        let mut incomes: Vec<f64> = self
+            .schedule()
+            .iter()
+            .chain(other.schedule())
+            .map(|knot| knot.income_limit() as f64)
+            .filter(|income| (start..=end).contains(income))
+            .collect();
+        incomes.push(start);
+        incomes.push(end);
+        incomes.sort_by(f64::total_cmp);
+        incomes.dedup();
+        if crossings {
+            let gap = |income: f64| self.tax_at(income) - other.tax_at(income);
+            let mut with_crossings = Vec::with_capacity(incomes.len());
+            for pair in incomes.windows(2) {
+                let (x0, x1) = (pair[0], pair[1]);
+                with_crossings.push(x0);
+                let (d0, d1) = (gap(x0), gap(x1));
+                if d0 * d1 < 0.0 {
+                    with_crossings.push(x0 + (x1 - x0) * d0 / (d0 - d1));
+                }
+            }
+            with_crossings.extend(incomes.last());
+            incomes = with_crossings;
+        }
+        Self::new(
+            incomes
+                .into_iter()
+                .map(|income| {
+                    IncomeTaxKnot::new(
+                        income as f32,
+                        op(self.tax_at(income), other.tax_at(income)) as f32,
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    fn common_bound(
+        a: &Self,
+        b: &Self,
+        knot: impl Fn(&Vec<IncomeTaxKnot>) -> Option<&IncomeTaxKnot>,
+        pick: impl Fn(f64, f64) -> f64,
+    ) -> Option<f64> {
+        let a = knot(a.schedule())?.income_limit() as f64;
+        let b = knot(b.schedule())?.income_limit() as f64;
+        Some(pick(a, b))
+    }
+
+    /// Tax at `income` in f64, holding the end values outside the knots.
+    fn tax_at(&self, income: f64) -> f64 {
+        let knots = self.schedule();
+        let next = knots.partition_point(|knot| knot.income_limit() as f64 <= income);
+        match (next.checked_sub(1).map(|i| &knots[i]), knots.get(next)) {
+            (Some(left), Some(right)) => {
+                let (x0, y0) = (left.income_limit() as f64, left.income_tax_amount() as f64);
+                let (x1, y1) = (
+                    right.income_limit() as f64,
+                    right.income_tax_amount() as f64,
+                );
+                y0 + (income - x0) * (y1 - y0) / (x1 - x0)
+            }
+            (Some(knot), None) | (None, Some(knot)) => knot.income_tax_amount() as f64,
+            (None, None) => 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::points::marginal_rate_knot::MarginalRateKnot;
+    use crate::core::points::tax_amount::IncomeTaxKnot;
+    use crate::core::schedules::amount_schedule::IncomeTaxAmountSchedule;
+    use crate::core::schedules::marginal_schedule::MarginalIncomeTaxRateSchedule;
+    use crate::errors::TaxError;
+
+    fn schedule(knots: &[(f32, f32)]) -> IncomeTaxAmountSchedule {
+        IncomeTaxAmountSchedule::new(
+            knots
+                .iter()
+                .map(|&(income, tax_amount)| IncomeTaxKnot::new(income, tax_amount))
+                .collect(),
+        )
+    }
+
+    fn marginal(brackets: &[(f32, f32)]) -> MarginalIncomeTaxRateSchedule {
+        MarginalIncomeTaxRateSchedule::new(
+            brackets
+                .iter()
+                .map(|&(limit, rate)| MarginalRateKnot::new(Some(limit), rate))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_add_and_subtract() {
+        // Federal 10% then 20% above 10000, plus a flat 5% state tax.
+        let federal =
+            marginal(&[(10000.0, 0.1), (f32::INFINITY, 0.2)]).to_income_amount_schedule(50000.0);
+        let state = marginal(&[(f32::INFINITY, 0.05)]).to_income_amount_schedule(40000.0);
+        let total = federal.add(&state);
+        // Only incomes both schedules cover are kept.
+        assert_eq!(
+            total,
+            schedule(&[(0.0, 0.0), (10000.0, 1500.0), (40000.0, 9000.0)])
+        );
+        let combined =
+            marginal(&[(10000.0, 0.15), (f32::INFINITY, 0.25)]).to_income_amount_schedule(40000.0);
+        let incomes = [0.0, 5000.0, 10000.0, 25000.0, 40000.0];
+        assert_eq!(
+            total.compute_income_taxes(&incomes),
+            combined.compute_income_taxes(&incomes)
+        );
+        assert_eq!(
+            total.subtract(&state),
+            schedule(&[(0.0, 0.0), (10000.0, 1000.0), (40000.0, 7000.0)])
+        );
+    }
+
+    #[test]
+    fn test_scale() {
+        let tax = schedule(&[(0.0, 0.0), (10000.0, 1000.0), (20000.0, 3000.0)]);
+        assert_eq!(
+            tax.scale(0.5),
+            schedule(&[(0.0, 0.0), (10000.0, 500.0), (20000.0, 1500.0)])
+        );
+    }
+
+    #[test]
+    fn test_pointwise_max_and_min() {
+        // A progressive tax crosses a flat 15% alternative at 15000.
+        let regular = schedule(&[(0.0, 0.0), (10000.0, 1000.0), (30000.0, 6000.0)]);
+        let alternative = schedule(&[(0.0, 0.0), (30000.0, 4500.0)]);
+        assert_eq!(
+            regular.pointwise_max(&alternative),
+            schedule(&[
+                (0.0, 0.0),
+                (10000.0, 1500.0),
+                (15000.0, 2250.0),
+                (30000.0, 6000.0)
+            ])
+        );
+        assert_eq!(
+            regular.pointwise_min(&alternative),
+            schedule(&[
+                (0.0, 0.0),
+                (10000.0, 1000.0),
+                (15000.0, 2250.0),
+                (30000.0, 4500.0)
+            ])
+        );
+    }
+
+    #[test]
+    fn test_shift() {
+        let tax = schedule(&[(0.0, 0.0), (10000.0, 1000.0), (20000.0, 3000.0)]);
+        // A 5000 deduction.
+        assert_eq!(
+            tax.shift(5000.0),
+            Ok(schedule(&[
+                (0.0, 0.0),
+                (5000.0, 0.0),
+                (15000.0, 1000.0),
+                (25000.0, 3000.0)
+            ]))
+        );
+        assert_eq!(
+            tax.shift(-15000.0),
+            Ok(schedule(&[(0.0, 2000.0), (5000.0, 3000.0)]))
+        );
+        assert_eq!(tax.shift(0.0), Ok(tax.clone()));
+        assert_eq!(
+            tax.shift(-20000.0),
+            Err(TaxError::IncomeOutOfBounds {
+                income: 20000.0,
+                bounds: (0.0, 20000.0)
+            })
+        );
+    }
+}
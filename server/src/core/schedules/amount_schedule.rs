@@ -1,12 +1,14 @@
+use crate::core::grid::IncomeGrid;
 use crate::core::points::tax_amount::{IncomeTaxKnot, IncomeTaxPoint};
-use crate::core::segment::LinearPiecewiseSegment;
+use crate::core::segment::{EffectiveRateSegment, LinearPiecewiseSegment};
 use crate::errors::TaxError;
-use crate::utils::{generate_range, group_incomes_by_segment};
+use crate::utils::group_incomes_by_segment;
 use rayon::prelude::*;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 /// Schedule representing how tax amounts change at each income threshold.
-#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, ToSchema)]
 pub struct IncomeTaxAmountSchedule {
     /// A sorted vector of points where the slope of the tax amount changes.
     schedule: Vec<IncomeTaxKnot>,
@@ -23,6 +25,127 @@ impl IncomeTaxAmountSchedule {
         &self.schedule
     }
 
+    /// Exact effective rate curve, one entry per segment between consecutive knots.
+    /// Zero-width segments are left out.
+    pub fn effective_rate_segments(&self) -> Vec<EffectiveRateSegment> {
+        self.schedule
+            .windows(2)
+            .filter(|knots| knots[0].income_limit() < knots[1].income_limit())
+            .map(|knots| {
+                LinearPiecewiseSegment {
+                    left_point: knots[0].clone(),
+                    right_point: knots[1].clone(),
+                }
+                .effective_rate_segment()
+            })
+            .collect()
+    }
+
+    /// Lowest income whose net income (income less tax) is `net_income`.
+    /// Net income is linear between knots, so the inverse is exact.
+    /// Where the marginal rate is 100% or more net income stays flat or falls, and a net
+    /// income reached again past such incomes has no single gross income.
+    pub fn gross_for_net(&self, net_income: f32) -> Result<f32, TaxError> {
+        let nets: Vec<f32> = self
+            .schedule
+            .iter()
+            .map(|knot| knot.income_limit() - knot.income_tax_amount())
+            .collect();
+        let knots = &self.schedule;
+        // The gross income and the index of the first knot at or above it.
+        let (gross, knot) = (0..knots.len())
+            .find_map(|i| {
+                if nets[i] == net_income {
+                    return Some((knots[i].income_limit(), i));
+                }
+                let next = i + 1;
+                if next < knots.len() && nets[i] < net_income && net_income <= nets[next] {
+                    let (x0, x1) = (knots[i].income_limit(), knots[next].income_limit());
+                    // Interpolating can round to just below the knot, so take it exactly.
+                    let gross = if net_income == nets[next] {
+                        x1
+                    } else {
+                        x0 + (net_income - nets[i]) * (x1 - x0) / (nets[next] - nets[i])
+                    };
+                    return Some((gross.min(x1), next));
+                }
+                None
+            })
+            .ok_or(TaxError::TargetOutOfBounds {
+                target: net_income,
+                bounds: (0.0, nets.iter().copied().fold(0.0, f32::max)),
+            })?;
+        // Net income only comes back down to the target past a marginal rate of 100% or more.
+        let reached_again = (knot + 1..knots.len())
+            .any(|k| knots[k].income_limit() > gross && nets[k] <= net_income);
+        if reached_again {
+            let k = (knot..knots.len() - 1)
+                .find(|&k| nets[k + 1] <= nets[k])
+                .ok_or_else(|| {
+                    TaxError::unprocessable_request(
+                        "net_income",
+                        format!("{} is reached again without net income falling", net_income),
+                    )
+                })?;
+            let (left, right) = (&knots[k], &knots[k + 1]);
+            return Err(TaxError::NonInvertible {
+                target: net_income,
+                income_start: left.income_limit(),
+                income_end: right.income_limit(),
+                marginal_rate: LinearPiecewiseSegment {
+                    left_point: left.clone(),
+                    right_point: right.clone(),
+                }
+                .slope(),
+            });
+        }
+        Ok(gross)
+    }
+
+    /// Lowest income taxed at an effective rate of `rate`.
+    /// Between knots the effective rate is `slope + intercept / income`, which inverts exactly.
+    /// A segment whose effective rate is constant at `rate` has no single income.
+    pub fn income_for_effective_rate(&self, rate: f32) -> Result<f32, TaxError> {
+        let segments = self.effective_rate_segments();
+        for segment in &segments {
+            if segment.intercept == 0.0 {
+                if segment.slope == rate {
+                    return Err(TaxError::NonInvertible {
+                        target: rate,
+                        income_start: segment.income_start,
+                        income_end: segment.income_end,
+                        marginal_rate: segment.slope,
+                    });
+                }
+                continue;
+            }
+            if segment.slope == rate {
+                continue;
+            }
+            let income = segment.intercept / (rate - segment.slope);
+            // Allow for rounding at the knots, which belong to both neighbouring segments.
+            let tolerance = f32::EPSILON * segment.income_end * 4.0;
+            if income > 0.0
+                && segment.income_start - tolerance <= income
+                && income <= segment.income_end + tolerance
+            {
+                return Ok(income.clamp(segment.income_start, segment.income_end));
+            }
+        }
+        let rates = self
+            .schedule
+            .iter()
+            .filter(|knot| knot.income_limit() > 0.0)
+            .map(|knot| knot.income_tax_amount() / knot.income_limit());
+        Err(TaxError::TargetOutOfBounds {
+            target: rate,
+            bounds: (
+                rates.clone().fold(f32::INFINITY, f32::min),
+                rates.fold(f32::NEG_INFINITY, f32::max),
+            ),
+        })
+    }
+
     /// Compute income tax amounts for a range of incomes
     pub fn compute_income_taxes_in_range(
         &self,
@@ -31,7 +154,8 @@ impl IncomeTaxAmountSchedule {
         income_step: f32,
     ) -> Result<Vec<f32>, TaxError> {
         // Not tested yet (its been tested with the endpoint, but not a formal software test)
-        let incomes_to_compute = generate_range(income_start, income_stop, income_step);
+        let incomes_to_compute =
+            IncomeGrid::linear(income_start, income_stop, income_step)?.incomes(self);
         self.compute_income_taxes(&incomes_to_compute)
     }
 
@@ -96,7 +220,7 @@ impl IncomeTaxAmountSchedule {
             });
         }
         // Choose not to parallelise the segments because the number of segments are usually low.
-        let grouped_income_values = group_incomes_by_segment(&incomes, &self.schedule);
+        let grouped_income_values = group_incomes_by_segment(incomes, &self.schedule);
         Ok(grouped_income_values
             .par_iter()
             .flat_map(|(segment, income_group)| {
@@ -111,11 +235,8 @@ impl IncomeTaxAmountSchedule {
     /// and then interpolate at some level of income in the segment.
     pub fn compute_specific_income_tax(&self, income: Option<f32>) -> Option<f32> {
         // TODO: This doesn't catch all the edge cases but should be good enough for now.
-        if income == None {
-            return None;
-        }
-        let income = income.unwrap();
-        if income < 0.0 {
+        let income = income?;
+        if income < 0.0 || income > self.schedule.last()?.income_limit() {
             return None;
         }
         let mut l = 0;
@@ -168,8 +289,10 @@ impl IncomeTaxAmountSchedule {
 
 #[cfg(test)]
 mod tests {
+    use crate::controller::taxes_config::TaxesConfig;
     use crate::core::points::tax_amount::{IncomeTaxKnot, IncomeTaxPoint};
     use crate::core::schedules::amount_schedule::IncomeTaxAmountSchedule;
+    use crate::core::segment::EffectiveRateSegment;
     use crate::errors::TaxError;
     use crate::utils::income_points_are_approx_eq;
     #[test]
@@ -222,6 +345,140 @@ mod tests {
         assert_eq!(zero_result, Some(0.0));
     }
 
+    #[test]
+    fn test_effective_rate_segments() {
+        let schedule = IncomeTaxAmountSchedule::new(vec![
+            IncomeTaxKnot::new(0.0, 0.0),
+            IncomeTaxKnot::new(10000.0, 1000.0),
+            IncomeTaxKnot::new(20000.0, 3000.0),
+            IncomeTaxKnot::new(100000.0, 27000.0),
+        ]);
+        let segments = schedule.effective_rate_segments();
+        assert_eq!(
+            segments,
+            vec![
+                EffectiveRateSegment {
+                    income_start: 0.0,
+                    income_end: 10000.0,
+                    slope: 0.1,
+                    intercept: 0.0
+                },
+                EffectiveRateSegment {
+                    income_start: 10000.0,
+                    income_end: 20000.0,
+                    slope: 0.2,
+                    intercept: -1000.0
+                },
+                EffectiveRateSegment {
+                    income_start: 20000.0,
+                    income_end: 100000.0,
+                    slope: 0.3,
+                    intercept: -3000.0
+                },
+            ]
+        );
+        // The parametric curve matches the sampled effective rate.
+        let segment = &segments[2];
+        let income = 25000.0;
+        let tax_amount = schedule.compute_specific_income_tax(Some(income)).unwrap();
+        assert!((segment.slope + segment.intercept / income - tax_amount / income).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_gross_for_net() {
+        // Net incomes at the knots are 0, 9000, 17000 and 73000.
+        let schedule = IncomeTaxAmountSchedule::new(vec![
+            IncomeTaxKnot::new(0.0, 0.0),
+            IncomeTaxKnot::new(10000.0, 1000.0),
+            IncomeTaxKnot::new(20000.0, 3000.0),
+            IncomeTaxKnot::new(100000.0, 27000.0),
+        ]);
+        assert_eq!(schedule.gross_for_net(0.0), Ok(0.0));
+        assert_eq!(schedule.gross_for_net(9000.0), Ok(10000.0));
+        assert_eq!(schedule.gross_for_net(13000.0), Ok(15000.0));
+        assert_eq!(
+            schedule.gross_for_net(80000.0),
+            Err(TaxError::TargetOutOfBounds {
+                target: 80000.0,
+                bounds: (0.0, 73000.0)
+            })
+        );
+
+        // A 150% marginal rate takes net income from 9000 back down to 8000.
+        let schedule = IncomeTaxAmountSchedule::new(vec![
+            IncomeTaxKnot::new(0.0, 0.0),
+            IncomeTaxKnot::new(10000.0, 1000.0),
+            IncomeTaxKnot::new(12000.0, 4000.0),
+            IncomeTaxKnot::new(20000.0, 5600.0),
+        ]);
+        assert_eq!(
+            schedule.gross_for_net(8500.0),
+            Err(TaxError::NonInvertible {
+                target: 8500.0,
+                income_start: 10000.0,
+                income_end: 12000.0,
+                marginal_rate: 1.5
+            })
+        );
+        // Net incomes above the dip have a single gross income.
+        assert_eq!(schedule.gross_for_net(10000.0), Ok(14500.0));
+    }
+
+    #[test]
+    fn test_gross_for_net_at_every_knot() {
+        // Interpolating towards a knot used to round just below it and report the knot's net
+        // income as reached again.
+        let taxes_config = TaxesConfig::load("assets/taxes.json").unwrap();
+        for (country, country_taxes) in &taxes_config.country_map {
+            for exchange_rate in [1.0, 0.05, 0.7, 1.3, 17.0, 83.0] {
+                let schedule = country_taxes
+                    .tax_schedule
+                    .exchange_rate_adjustment(&Some(exchange_rate))
+                    .to_income_amount_schedule(10000000.0 / exchange_rate);
+                for knot in schedule.schedule() {
+                    let net_income = knot.income_limit() - knot.income_tax_amount();
+                    assert_eq!(
+                        schedule.gross_for_net(net_income),
+                        Ok(knot.income_limit()),
+                        "{} at exchange rate {}",
+                        country,
+                        exchange_rate
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_income_for_effective_rate() {
+        let schedule = IncomeTaxAmountSchedule::new(vec![
+            IncomeTaxKnot::new(0.0, 0.0),
+            IncomeTaxKnot::new(10000.0, 1000.0),
+            IncomeTaxKnot::new(20000.0, 3000.0),
+            IncomeTaxKnot::new(100000.0, 27000.0),
+        ]);
+        assert_eq!(schedule.income_for_effective_rate(0.15), Ok(20000.0));
+        let income = schedule.income_for_effective_rate(0.2).unwrap();
+        assert!((income - 30000.0).abs() < 0.01, "{}", income);
+        // Every income up to 10000 is taxed at 10%.
+        assert_eq!(
+            schedule.income_for_effective_rate(0.1),
+            Err(TaxError::NonInvertible {
+                target: 0.1,
+                income_start: 0.0,
+                income_end: 10000.0,
+                marginal_rate: 0.1
+            })
+        );
+        assert_eq!(
+            schedule.income_for_effective_rate(0.3),
+            Err(TaxError::TargetOutOfBounds {
+                target: 0.3,
+                bounds: (0.1, 0.27)
+            })
+        );
+    }
+
     #[test]
     fn test_get_breakeven_taxes() {
         // https://www.desmos.com/calculator
@@ -1,17 +1,30 @@
 use crate::core::points::marginal_rate_knot::MarginalRateKnot;
 use crate::core::points::tax_amount::IncomeTaxKnot;
 use crate::errors::TaxError;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 use super::amount_schedule::IncomeTaxAmountSchedule;
 
 /// A schedule characterised by changes in marginal rates.
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
 pub struct MarginalIncomeTaxRateSchedule {
     /// A sorted vector of points where the marginal tax rates change.
     schedule: Vec<MarginalRateKnot>,
 }
 
+/// Tax contributed by one bracket of a marginal rate schedule.
+#[derive(Clone, Debug, PartialEq, Serialize, ToSchema)]
+pub struct BracketTax {
+    pub income_start: f32,
+    /// `None` for the top bracket, whose rate carries on indefinitely.
+    pub income_end: Option<f32>,
+    pub marginal_rate: f32,
+    /// Part of the income falling within the bracket.
+    pub income_taxed: f32,
+    pub tax_amount: f32,
+}
+
 /// An income tax table in terms of marginal rates and income thresholds
 impl MarginalIncomeTaxRateSchedule {
     pub fn schedule(&self) -> &Vec<MarginalRateKnot> {
@@ -49,6 +62,76 @@ impl MarginalIncomeTaxRateSchedule {
         Ok(tax_amount)
     }
 
+    /// Index of the bracket the next unit of income above `income` falls in.
+    /// Each knot's rate applies up to its income limit, and the last rate applies beyond it.
+    pub fn bracket_index_at(&self, income: f32) -> Result<Option<usize>, TaxError> {
+        if income < 0.0 {
+            return Err(TaxError::NegativeIncome(income));
+        }
+        Ok(self
+            .schedule
+            .iter()
+            .position(|knot| knot.income_limit().is_none_or(|limit| income < limit))
+            .or(self.schedule.len().checked_sub(1)))
+    }
+
+    /// Marginal rate paid on the next unit of income above `income`.
+    pub fn marginal_rate_at(&self, income: f32) -> Result<f32, TaxError> {
+        Ok(self
+            .bracket_index_at(income)?
+            .map(|i| self.schedule[i].marginal_rate())
+            .unwrap_or(0.0))
+    }
+
+    /// Tax each bracket contributes at `income`, one entry per knot, given the `tax_amount`
+    /// at `income`. Contributions are computed in f64 and rounded, and the last bracket with
+    /// income in it takes the rounding remainder, so the entries sum in order to exactly
+    /// `tax_amount`. When no f32 remainder added to the lower brackets' sum rounds to
+    /// `tax_amount`, `TargetOutOfBounds` is returned instead.
+    pub fn bracket_breakdown(
+        &self,
+        income: f32,
+        tax_amount: f32,
+    ) -> Result<Vec<BracketTax>, TaxError> {
+        if income < 0.0 {
+            return Err(TaxError::NegativeIncome(income));
+        }
+        let last = self.schedule.len().saturating_sub(1);
+        let mut income_start = 0.0;
+        let mut brackets: Vec<BracketTax> = self
+            .schedule
+            .iter()
+            .enumerate()
+            .map(|(i, knot)| {
+                let income_end = knot
+                    .income_limit()
+                    .filter(|limit| i != last && limit.is_finite());
+                let upper = income_end.unwrap_or(f32::INFINITY).min(income);
+                let income_taxed = (upper - income_start).max(0.0);
+                let bracket = BracketTax {
+                    income_start,
+                    income_end,
+                    marginal_rate: knot.marginal_rate(),
+                    income_taxed,
+                    tax_amount: (knot.marginal_rate() as f64 * income_taxed as f64) as f32,
+                };
+                income_start = income_end.unwrap_or(income_start);
+                bracket
+            })
+            .collect();
+        if let Some(top) = brackets
+            .iter()
+            .rposition(|bracket| bracket.income_taxed > 0.0)
+        {
+            let below: f32 = brackets[..top]
+                .iter()
+                .map(|bracket| bracket.tax_amount)
+                .sum();
+            brackets[top].tax_amount = remainder_to_reach(below, tax_amount)?;
+        }
+        Ok(brackets)
+    }
+
     /// Adjust the marginal amount schedule according to an exchange rate
     pub fn exchange_rate_adjustment(&self, exchange_rate: &Option<f32>) -> Self {
         match exchange_rate {
@@ -97,12 +180,76 @@ impl MarginalIncomeTaxRateSchedule {
     }
 }
 
+/// The f32 `remainder` for which `below + remainder` rounds to exactly `target`.
+/// The sum only grows with `remainder`, so the remainder is searched over the ordered f32
+/// bit patterns, however many ulps away: outwards from the rounded f64 difference, then
+/// by bisection. When no remainder reaches `target`, the error's `bounds` are the closest
+/// sums on either side.
+fn remainder_to_reach(below: f32, target: f32) -> Result<f32, TaxError> {
+    // Orders f32 bit patterns as `f32::total_cmp` does, and is its own inverse.
+    let key = |x: f32| {
+        let bits = x.to_bits() as i32;
+        (bits ^ (((bits >> 31) as u32) >> 1) as i32) as i64
+    };
+    let from_key = |key: i64| {
+        let bits = key as i32;
+        f32::from_bits((bits ^ (((bits >> 31) as u32) >> 1) as i32) as u32)
+    };
+    let start = (target as f64 - below as f64) as f32;
+    let start_sum = below + start;
+    if start_sum == target {
+        return Ok(start);
+    }
+    // Grow a bracket of keys around the remainder, one end below `target` and one not.
+    let direction = if start_sum < target { 1 } else { -1 };
+    let mut near = key(start);
+    let mut step = 1;
+    let far = loop {
+        let far = near + direction * step;
+        let remainder = from_key(far);
+        if !remainder.is_finite() {
+            return Err(TaxError::TargetOutOfBounds {
+                target,
+                bounds: (start_sum, start_sum),
+            });
+        }
+        if (below + remainder < target) != (direction == 1) {
+            break far;
+        }
+        near = far;
+        step *= 2;
+    };
+    // The smallest key whose sum is not below `target` is the only one that can reach it.
+    let (mut low, mut high) = if direction == 1 {
+        (near, far)
+    } else {
+        (far, near)
+    };
+    while high - low > 1 {
+        let mid = low + (high - low) / 2;
+        if below + from_key(mid) < target {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    let remainder = from_key(high);
+    if below + remainder == target {
+        Ok(remainder)
+    } else {
+        Err(TaxError::TargetOutOfBounds {
+            target,
+            bounds: (below + from_key(low), below + remainder),
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
     use crate::core::points::marginal_rate_knot::MarginalRateKnot;
     use crate::core::points::tax_amount::IncomeTaxKnot;
-    use crate::core::schedules::marginal_schedule::MarginalIncomeTaxRateSchedule;
+    use crate::core::schedules::marginal_schedule::{BracketTax, MarginalIncomeTaxRateSchedule};
     use crate::errors::TaxError;
 
     #[test]
@@ -149,4 +296,170 @@ mod tests {
         let zero_result = schedule.get_tax_amount_from_marginal_rates_knots(0.0);
         assert_eq!(zero_result.unwrap(), 0.0);
     }
+
+    #[test]
+    fn test_marginal_rate_at() {
+        let schedule = MarginalIncomeTaxRateSchedule {
+            schedule: vec![
+                MarginalRateKnot::new(Some(10000.0), 0.1),
+                MarginalRateKnot::new(Some(20000.0), 0.2),
+                MarginalRateKnot::new(Some(f32::INFINITY), 0.3),
+            ],
+        };
+        assert_eq!(schedule.marginal_rate_at(0.0), Ok(0.1));
+        assert_eq!(schedule.marginal_rate_at(9999.0), Ok(0.1));
+        // The next unit of income at a threshold falls in the bracket above.
+        assert_eq!(schedule.marginal_rate_at(10000.0), Ok(0.2));
+        assert_eq!(schedule.marginal_rate_at(25000.0), Ok(0.3));
+        assert_eq!(
+            schedule.marginal_rate_at(-1.0),
+            Err(TaxError::NegativeIncome(-1.0))
+        );
+
+        // The last rate carries on past a bounded last bracket.
+        let bounded = MarginalIncomeTaxRateSchedule {
+            schedule: vec![
+                MarginalRateKnot::new(Some(10000.0), 0.1),
+                MarginalRateKnot::new(Some(20000.0), 0.2),
+            ],
+        };
+        assert_eq!(bounded.marginal_rate_at(30000.0), Ok(0.2));
+    }
+
+    #[test]
+    fn test_bracket_breakdown() {
+        let schedule = MarginalIncomeTaxRateSchedule {
+            schedule: vec![
+                MarginalRateKnot::new(Some(10000.0), 0.1),
+                MarginalRateKnot::new(Some(20000.0), 0.2),
+                MarginalRateKnot::new(Some(f32::INFINITY), 0.3),
+            ],
+        };
+        let breakdown = schedule.bracket_breakdown(25000.0, 4500.0).unwrap();
+        assert_eq!(
+            breakdown,
+            vec![
+                BracketTax {
+                    income_start: 0.0,
+                    income_end: Some(10000.0),
+                    marginal_rate: 0.1,
+                    income_taxed: 10000.0,
+                    tax_amount: 1000.0,
+                },
+                BracketTax {
+                    income_start: 10000.0,
+                    income_end: Some(20000.0),
+                    marginal_rate: 0.2,
+                    income_taxed: 10000.0,
+                    tax_amount: 2000.0,
+                },
+                BracketTax {
+                    income_start: 20000.0,
+                    income_end: None,
+                    marginal_rate: 0.3,
+                    income_taxed: 5000.0,
+                    tax_amount: 1500.0,
+                },
+            ]
+        );
+        assert_eq!(schedule.bracket_index_at(25000.0), Ok(Some(2)));
+
+        // Brackets above the income contribute nothing.
+        let breakdown = schedule.bracket_breakdown(5000.0, 500.0).unwrap();
+        assert_eq!(
+            breakdown
+                .iter()
+                .map(|bracket| bracket.income_taxed)
+                .collect::<Vec<_>>(),
+            vec![5000.0, 0.0, 0.0]
+        );
+        assert_eq!(schedule.bracket_index_at(5000.0), Ok(Some(0)));
+
+        // A bounded last bracket carries on past its limit, as in the tax amount.
+        let bounded = MarginalIncomeTaxRateSchedule {
+            schedule: vec![
+                MarginalRateKnot::new(Some(10000.0), 0.1),
+                MarginalRateKnot::new(Some(20000.0), 0.2),
+            ],
+        };
+        let tax_amount = bounded
+            .get_tax_amount_from_marginal_rates_knots(30000.0)
+            .unwrap();
+        let breakdown = bounded.bracket_breakdown(30000.0, tax_amount).unwrap();
+        assert_eq!(breakdown[1].income_end, None);
+        assert_eq!(breakdown[1].income_taxed, 20000.0);
+
+        // The entries sum to the tax amount exactly, even where f32 rounding would not.
+        let awkward = MarginalIncomeTaxRateSchedule {
+            schedule: vec![
+                MarginalRateKnot::new(Some(14000.0), 0.105),
+                MarginalRateKnot::new(Some(48000.0), 0.175),
+                MarginalRateKnot::new(Some(70000.0), 0.3),
+                MarginalRateKnot::new(Some(f32::INFINITY), 0.33),
+            ],
+        };
+        for income in [
+            0.0, 1.0, 13999.9, 14000.0, 33333.3, 48000.1, 69999.99, 123456.7,
+        ] {
+            let tax_amount = awkward
+                .to_income_amount_schedule(200000.0)
+                .compute_specific_income_tax(Some(income))
+                .unwrap();
+            let breakdown = awkward.bracket_breakdown(income, tax_amount).unwrap();
+            assert_eq!(
+                breakdown
+                    .iter()
+                    .map(|bracket| bracket.tax_amount)
+                    .sum::<f32>(),
+                tax_amount
+            );
+        }
+        assert_eq!(
+            schedule.bracket_breakdown(-1.0, 0.0),
+            Err(TaxError::NegativeIncome(-1.0))
+        );
+    }
+
+    #[test]
+    fn test_bracket_breakdown_with_large_lower_brackets() {
+        // The lower brackets' tax dwarfs the top bracket's, so the remainder's ulps are far
+        // finer than the sum's, and a fixed number of nudges would not be enough.
+        let schedule = MarginalIncomeTaxRateSchedule {
+            schedule: vec![
+                MarginalRateKnot::new(Some(123456.7), 0.105),
+                MarginalRateKnot::new(Some(98765432.0), 0.377),
+                MarginalRateKnot::new(Some(f32::INFINITY), 0.451),
+            ],
+        };
+        let amounts = schedule.to_income_amount_schedule(2e8);
+        for income in [98765440.0, 98765450.0, 98765500.0, 98770000.0] {
+            let tax_amount = amounts.compute_specific_income_tax(Some(income)).unwrap();
+            let breakdown = schedule.bracket_breakdown(income, tax_amount).unwrap();
+            assert_eq!(
+                breakdown
+                    .iter()
+                    .map(|bracket| bracket.tax_amount)
+                    .sum::<f32>(),
+                tax_amount,
+                "{}",
+                income
+            );
+        }
+
+        // No f32 added to 1 gives 2^24 + 2, as the sums in between are halfway cases that
+        // round to their even neighbours.
+        let flat = MarginalIncomeTaxRateSchedule {
+            schedule: vec![
+                MarginalRateKnot::new(Some(2.0), 0.5),
+                MarginalRateKnot::new(Some(f32::INFINITY), 0.5),
+            ],
+        };
+        assert_eq!(
+            flat.bracket_breakdown(33554436.0, 16777218.0),
+            Err(TaxError::TargetOutOfBounds {
+                target: 16777218.0,
+                bounds: (16777216.0, 16777220.0),
+            })
+        );
+    }
 }
@@ -1,2 +1,3 @@
+pub mod algebra;
 pub mod amount_schedule;
 pub mod marginal_schedule;
@@ -1,5 +1,7 @@
 use crate::core::points::tax_amount::{IncomeTaxKnot, IncomeTaxPoint};
+use serde::Serialize;
 use std::cmp::Ordering;
+use utoipa::ToSchema;
 
 /// A line segment characterised by two points
 #[derive(Clone, Debug, PartialEq)]
@@ -10,6 +12,19 @@ pub struct LinearPiecewiseSegment {
     pub right_point: IncomeTaxKnot,
 }
 
+/// The effective tax rate over one segment of a tax amount schedule.
+/// Within a segment tax is `intercept + slope * income`, so the effective rate at an income
+/// in `[income_start, income_end]` is exactly `slope + intercept / income`.
+#[derive(Clone, Debug, PartialEq, Serialize, ToSchema)]
+pub struct EffectiveRateSegment {
+    pub income_start: f32,
+    pub income_end: f32,
+    /// Marginal rate over the segment.
+    pub slope: f32,
+    /// Tax amount the segment's line reaches at zero income.
+    pub intercept: f32,
+}
+
 /// Linearly interpolate a line segment at an income value, to get a taxation value.
 impl LinearPiecewiseSegment {
     pub fn new(left_point: IncomeTaxKnot, right_point: IncomeTaxKnot) -> Result<Self, String> {
@@ -26,6 +41,26 @@ impl LinearPiecewiseSegment {
         }
     }
 
+    /// Change in tax amount per unit of income.
+    pub fn slope(&self) -> f32 {
+        (self.right_point.income_tax_amount() - self.left_point.income_tax_amount())
+            / (self.right_point.income_limit() - self.left_point.income_limit())
+    }
+
+    /// Tax amount where the segment's line meets zero income.
+    pub fn intercept(&self) -> f32 {
+        self.left_point.income_tax_amount() - self.slope() * self.left_point.income_limit()
+    }
+
+    pub fn effective_rate_segment(&self) -> EffectiveRateSegment {
+        EffectiveRateSegment {
+            income_start: self.left_point.income_limit(),
+            income_end: self.right_point.income_limit(),
+            slope: self.slope(),
+            intercept: self.intercept(),
+        }
+    }
+
     /// Linear interpolation
     pub fn linear_interpolation(&self, income: f32) -> Option<f32> {
         if income
@@ -41,6 +76,10 @@ impl LinearPiecewiseSegment {
         {
             return None;
         }
+        // A zero-width segment, e.g. a first bracket ending at zero, is just its knot.
+        if self.left_point.income_limit() == self.right_point.income_limit() {
+            return Some(self.left_point.income_tax_amount());
+        }
         Some(
             self.left_point.income_tax_amount()
                 + (self.right_point.income_tax_amount() - self.left_point.income_tax_amount())
@@ -89,7 +128,7 @@ impl LinearPiecewiseSegment {
                     Some(solution) => {
                         // Verify solution satisfies t \in [0, 1]
                         let (t1, t2) = (solution[(0, 0)], solution[(1, 0)]);
-                        if (0.0 <= t1 && t1 <= 1.0) && (0.0 <= t2 && t2 <= 1.0) {
+                        if (0.0..=1.0).contains(&t1) && (0.0..=1.0).contains(&t2) {
                             Some(IncomeTaxPoint::new(
                                 x1 + t1 * (x2 - x1),
                                 y1 + t1 * (y2 - y1),
@@ -126,6 +165,12 @@ mod tests {
 
         let invalid_result_2 = segment.linear_interpolation(3.9);
         assert_eq!(invalid_result_2, None);
+
+        let zero_width = LinearPiecewiseSegment {
+            left_point: IncomeTaxKnot::new(0.0, 0.0),
+            right_point: IncomeTaxKnot::new(0.0, 0.0),
+        };
+        assert_eq!(zero_width.linear_interpolation(0.0), Some(0.0));
     }
 
     #[test]
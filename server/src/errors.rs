@@ -1,7 +1,62 @@
-#[derive(Debug, PartialEq)]
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError};
+use chrono::NaiveDate;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum TaxError {
     NegativeIncome(f32),
-    IncomeOutOfBounds { income: f32, bounds: (f32, f32) },
+    IncomeOutOfBounds {
+        income: f32,
+        bounds: (f32, f32),
+    },
+    /// No exchange rate makes two schedules tax an income the same.
+    NoTieExchangeRate {
+        income: f32,
+    },
+    /// No income within `bounds` of a schedule gives the requested net income or rate.
+    TargetOutOfBounds {
+        target: f32,
+        bounds: (f32, f32),
+    },
+    /// More than one income gives `target`, because of incomes between `income_start` and
+    /// `income_end` taxed at `marginal_rate`.
+    NonInvertible {
+        target: f32,
+        income_start: f32,
+        income_end: f32,
+        marginal_rate: f32,
+    },
+    /// A request field is malformed or out of range.
+    InvalidRequest {
+        field: String,
+        message: String,
+    },
+    /// A request field is well formed but cannot be honoured, usually alongside other fields.
+    UnprocessableRequest {
+        field: String,
+        message: String,
+    },
+    UnknownCountry(String),
+    UnknownCurrency(String),
+    ExchangeRate(ExchangeRateError),
+}
+
+impl TaxError {
+    pub fn invalid_request(field: &str, message: impl Into<String>) -> Self {
+        TaxError::InvalidRequest {
+            field: field.to_string(),
+            message: message.into(),
+        }
+    }
+
+    pub fn unprocessable_request(field: &str, message: impl Into<String>) -> Self {
+        TaxError::UnprocessableRequest {
+            field: field.to_string(),
+            message: message.into(),
+        }
+    }
 }
 
 impl std::fmt::Display for TaxError {
@@ -13,8 +68,217 @@ impl std::fmt::Display for TaxError {
             TaxError::IncomeOutOfBounds { income, bounds } => {
                 write!(f, "Income {} is out of bounds: {:?}", income, bounds)
             }
+            TaxError::NoTieExchangeRate { income } => {
+                write!(f, "No exchange rate equalises taxes at income {}", income)
+            }
+            TaxError::TargetOutOfBounds { target, bounds } => {
+                write!(
+                    f,
+                    "No income gives {}, which is out of bounds: {:?}",
+                    target, bounds
+                )
+            }
+            TaxError::NonInvertible {
+                target,
+                income_start,
+                income_end,
+                marginal_rate,
+            } => write!(
+                f,
+                "More than one income gives {}: the marginal rate is {} between {} and {}",
+                target, marginal_rate, income_start, income_end
+            ),
+            TaxError::InvalidRequest { field, message }
+            | TaxError::UnprocessableRequest { field, message } => {
+                write!(f, "Invalid {}: {}", field, message)
+            }
+            TaxError::UnknownCountry(country) => write!(f, "Unknown country {}", country),
+            TaxError::UnknownCurrency(currency) => write!(f, "Unknown currency {}", currency),
+            TaxError::ExchangeRate(err) => write!(f, "{}", err),
         }
     }
 }
 
 impl std::error::Error for TaxError {}
+
+impl From<ExchangeRateError> for TaxError {
+    fn from(err: ExchangeRateError) -> Self {
+        match err {
+            ExchangeRateError::UnsupportedBaseCurrency(currency)
+            | ExchangeRateError::UnknownCurrency(currency) => TaxError::UnknownCurrency(currency),
+            err => TaxError::ExchangeRate(err),
+        }
+    }
+}
+
+/// JSON body of an error response.
+#[derive(Serialize, ToSchema)]
+pub struct ErrorBody<'a> {
+    /// Machine readable error kind, e.g. `unknown_country`.
+    pub error: &'a str,
+    pub message: String,
+    /// Request field at fault.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub country: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub currency: Option<&'a str>,
+}
+
+impl ResponseError for TaxError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            TaxError::NegativeIncome(_) | TaxError::InvalidRequest { .. } => {
+                StatusCode::BAD_REQUEST
+            }
+            TaxError::UnknownCountry(_) | TaxError::UnknownCurrency(_) => StatusCode::NOT_FOUND,
+            TaxError::IncomeOutOfBounds { .. }
+            | TaxError::NoTieExchangeRate { .. }
+            | TaxError::TargetOutOfBounds { .. }
+            | TaxError::NonInvertible { .. }
+            | TaxError::UnprocessableRequest { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            TaxError::ExchangeRate(err) => match err {
+                // The rates source failed rather than the request.
+                ExchangeRateError::Request(_)
+                | ExchangeRateError::Parse(_)
+                | ExchangeRateError::Io(_) => StatusCode::BAD_GATEWAY,
+                _ => StatusCode::UNPROCESSABLE_ENTITY,
+            },
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let (error, field, country, currency) = match self {
+            TaxError::NegativeIncome(_) => ("negative_income", None, None, None),
+            TaxError::IncomeOutOfBounds { .. } => ("income_out_of_bounds", None, None, None),
+            TaxError::NoTieExchangeRate { .. } => ("no_tie_exchange_rate", None, None, None),
+            TaxError::TargetOutOfBounds { .. } => ("target_out_of_bounds", None, None, None),
+            TaxError::NonInvertible { .. } => ("non_invertible", None, None, None),
+            TaxError::InvalidRequest { field, .. } => {
+                ("invalid_request", Some(field.as_str()), None, None)
+            }
+            TaxError::UnprocessableRequest { field, .. } => {
+                ("unprocessable_request", Some(field.as_str()), None, None)
+            }
+            TaxError::UnknownCountry(country) => {
+                ("unknown_country", None, Some(country.as_str()), None)
+            }
+            TaxError::UnknownCurrency(currency) => {
+                ("unknown_currency", None, None, Some(currency.as_str()))
+            }
+            TaxError::ExchangeRate(ExchangeRateError::NoPppConversionFactor(country)) => {
+                ("exchange_rate", None, Some(country.as_str()), None)
+            }
+            TaxError::ExchangeRate(_) => ("exchange_rate", None, None, None),
+        };
+        HttpResponse::build(self.status_code()).json(ErrorBody {
+            error,
+            message: self.to_string(),
+            field,
+            country,
+            currency,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExchangeRateError {
+    Request(String),
+    Parse(String),
+    Io(String),
+    UnsupportedBaseCurrency(String),
+    UnknownCurrency(String),
+    NoHistoricalRates(NaiveDate),
+    NoPppConversionFactor(String),
+    NotEnoughHistoricalRates {
+        start: NaiveDate,
+        end: NaiveDate,
+        found: usize,
+    },
+}
+
+impl std::fmt::Display for ExchangeRateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExchangeRateError::Request(msg) => {
+                write!(f, "Exchange rate request failed: {}", msg)
+            }
+            ExchangeRateError::Parse(msg) => {
+                write!(f, "Exchange rates could not be parsed: {}", msg)
+            }
+            ExchangeRateError::Io(msg) => {
+                write!(f, "Exchange rates could not be read: {}", msg)
+            }
+            ExchangeRateError::UnsupportedBaseCurrency(currency) => {
+                write!(
+                    f,
+                    "No exchange rates available for base currency {}",
+                    currency
+                )
+            }
+            ExchangeRateError::UnknownCurrency(currency) => {
+                write!(f, "No exchange rate available for currency {}", currency)
+            }
+            ExchangeRateError::NoHistoricalRates(date) => {
+                write!(f, "No exchange rates available on or before {}", date)
+            }
+            ExchangeRateError::NoPppConversionFactor(country) => {
+                write!(f, "No PPP conversion factor available for {}", country)
+            }
+            ExchangeRateError::NotEnoughHistoricalRates { start, end, found } => {
+                write!(
+                    f,
+                    "Need at least two historical rates between {} and {}, found {}",
+                    start, end, found
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ExchangeRateError {}
+
+/// A problem with one country's entry in the taxes config.
+#[derive(Debug, PartialEq)]
+pub struct ConfigIssue {
+    pub country: String,
+    /// Index of the offending bracket in the country's schedule, if any.
+    pub bracket: Option<usize>,
+    pub message: String,
+}
+
+impl std::fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.bracket {
+            Some(bracket) => write!(f, "{}, bracket {}: {}", self.country, bracket, self.message),
+            None => write!(f, "{}: {}", self.country, self.message),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ConfigError {
+    Io(String),
+    Parse(String),
+    /// Every validation failure found in the config.
+    Invalid(Vec<ConfigIssue>),
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Io(msg) => write!(f, "Taxes config could not be read: {}", msg),
+            ConfigError::Parse(msg) => write!(f, "Taxes config could not be parsed: {}", msg),
+            ConfigError::Invalid(issues) => {
+                write!(f, "Taxes config is invalid:")?;
+                for issue in issues {
+                    write!(f, "\n  {}", issue)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
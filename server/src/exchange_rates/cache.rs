@@ -0,0 +1,347 @@
+use crate::errors::ExchangeRateError;
+use crate::exchange_rates::{ExchangeRateProvider, ExchangeRateTable};
+use async_trait::async_trait;
+use log::{info, warn};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+
+/// Minimum number of seconds between two refreshes of the same base currency.
+/// Stops a failing or lagging provider from being hit on every request.
+const MIN_REFRESH_INTERVAL_SECS: u64 = 60;
+
+/// Longest wait between retries of a failing refresh.
+const MAX_RETRY_INTERVAL_SECS: u64 = 3600;
+
+struct CacheEntry {
+    table: ExchangeRateTable,
+    /// Unix time after which the rates should be refreshed, `None` to keep them forever.
+    refresh_after: Option<u64>,
+    refreshing: bool,
+    /// Refreshes failed in a row.
+    failures: u32,
+}
+
+/// Thread-safe exchange rate cache keyed by base currency, shared between actix workers.
+/// Rates are served from the cache until the provider's next update time, after which
+/// they keep being served while a refresh runs in the background.
+/// If the refresh fails the cached rates are served with `stale` set.
+pub struct ExchangeRateCache {
+    provider: Arc<dyn ExchangeRateProvider>,
+    entries: Arc<RwLock<HashMap<String, CacheEntry>>>,
+    /// Current unix time in seconds.
+    clock: Arc<dyn Fn() -> u64 + Send + Sync>,
+}
+
+fn now_unix() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+fn refresh_after(table: &ExchangeRateTable, now: u64) -> Option<u64> {
+    table
+        .time_next_update_unix
+        .map(|next_update| next_update.max(now + MIN_REFRESH_INTERVAL_SECS))
+}
+
+/// When to retry after the `failures`-th refresh in a row failed at `failed_at`.
+/// The wait doubles with each failure up to `MAX_RETRY_INTERVAL_SECS`, but while the cached
+/// rates have not reached their end of life a retry is made by then.
+fn retry_after(table: &ExchangeRateTable, failures: u32, failed_at: u64) -> u64 {
+    let backoff = MIN_REFRESH_INTERVAL_SECS
+        .saturating_mul(1 << failures.saturating_sub(1).min(16))
+        .min(MAX_RETRY_INTERVAL_SECS);
+    let retry = failed_at + backoff;
+    match table.time_eol_unix {
+        Some(eol) if eol > failed_at => retry.min(eol.max(failed_at + MIN_REFRESH_INTERVAL_SECS)),
+        _ => retry,
+    }
+}
+
+impl ExchangeRateCache {
+    pub fn new(provider: Arc<dyn ExchangeRateProvider>) -> Self {
+        Self::with_clock(provider, Arc::new(now_unix))
+    }
+
+    fn with_clock(
+        provider: Arc<dyn ExchangeRateProvider>,
+        clock: Arc<dyn Fn() -> u64 + Send + Sync>,
+    ) -> Self {
+        Self {
+            provider,
+            entries: Arc::new(RwLock::new(HashMap::new())),
+            clock,
+        }
+    }
+
+    /// Serve rates, along with the background refresh this request started, if any.
+    async fn fetch_and_refresh(
+        &self,
+        base_currency: &str,
+    ) -> Result<(ExchangeRateTable, Option<JoinHandle<()>>), ExchangeRateError> {
+        let now = (self.clock)();
+        let cached = {
+            let mut entries = self.entries.write().unwrap();
+            entries.get_mut(base_currency).map(|entry| {
+                let expired = entry
+                    .refresh_after
+                    .is_some_and(|refresh_after| now >= refresh_after);
+                let start_refresh = expired && !entry.refreshing;
+                if start_refresh {
+                    entry.refreshing = true;
+                }
+                let mut table = entry.table.clone();
+                if table.time_eol_unix.is_some_and(|eol| now >= eol) {
+                    table.stale = true;
+                }
+                (table, start_refresh)
+            })
+        };
+
+        match cached {
+            Some((table, start_refresh)) => {
+                let refresh = start_refresh.then(|| self.spawn_refresh(base_currency));
+                Ok((table, refresh))
+            }
+            None => {
+                let table = self.provider.fetch_exchange_rates(base_currency).await?;
+                self.entries.write().unwrap().insert(
+                    base_currency.to_string(),
+                    CacheEntry {
+                        refresh_after: refresh_after(&table, (self.clock)()),
+                        table: table.clone(),
+                        refreshing: false,
+                        failures: 0,
+                    },
+                );
+                Ok((table, None))
+            }
+        }
+    }
+
+    /// Refresh in the background. The next refresh is timed from when this one finishes.
+    fn spawn_refresh(&self, base_currency: &str) -> JoinHandle<()> {
+        let provider = self.provider.clone();
+        let entries = self.entries.clone();
+        let clock = self.clock.clone();
+        let base_currency = base_currency.to_string();
+        tokio::spawn(async move {
+            let result = provider.fetch_exchange_rates(&base_currency).await;
+            let now = clock();
+            let mut entries = entries.write().unwrap();
+            let Some(entry) = entries.get_mut(&base_currency) else {
+                return;
+            };
+            entry.refreshing = false;
+            match result {
+                Ok(table) => {
+                    info!("Refreshed exchange rates for {}", base_currency);
+                    entry.refresh_after = refresh_after(&table, now);
+                    entry.table = table;
+                    entry.failures = 0;
+                }
+                Err(err) => {
+                    warn!(
+                        "Serving stale exchange rates for {}, refresh failed: {}",
+                        base_currency, err
+                    );
+                    entry.failures += 1;
+                    entry.refresh_after = Some(retry_after(&entry.table, entry.failures, now));
+                    entry.table.stale = true;
+                }
+            }
+        })
+    }
+}
+
+#[async_trait]
+impl ExchangeRateProvider for ExchangeRateCache {
+    async fn fetch_exchange_rates(
+        &self,
+        base_currency: &str,
+    ) -> Result<ExchangeRateTable, ExchangeRateError> {
+        self.fetch_and_refresh(base_currency)
+            .await
+            .map(|(table, _)| table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::ExchangeRateError;
+    use crate::exchange_rates::cache::ExchangeRateCache;
+    use crate::exchange_rates::{ExchangeRateProvider, ExchangeRateTable};
+    use async_trait::async_trait;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Counts fetches, and fails every fetch after the first `successes`.
+    struct CountingProvider {
+        fetches: AtomicUsize,
+        successes: usize,
+        time_next_update_unix: u64,
+        time_eol_unix: Option<u64>,
+    }
+
+    impl CountingProvider {
+        fn new(successes: usize) -> Self {
+            Self {
+                fetches: AtomicUsize::new(0),
+                successes,
+                time_next_update_unix: 1000,
+                time_eol_unix: None,
+            }
+        }
+    }
+
+    #[async_trait]
+    impl ExchangeRateProvider for CountingProvider {
+        async fn fetch_exchange_rates(
+            &self,
+            base_currency: &str,
+        ) -> Result<ExchangeRateTable, ExchangeRateError> {
+            let fetches = self.fetches.fetch_add(1, Ordering::SeqCst) + 1;
+            if fetches > self.successes {
+                return Err(ExchangeRateError::Request("offline".to_string()));
+            }
+            Ok(ExchangeRateTable {
+                time_next_update_unix: Some(self.time_next_update_unix),
+                time_eol_unix: self.time_eol_unix,
+                ..ExchangeRateTable::new(
+                    base_currency,
+                    HashMap::from([("AUD".to_string(), fetches as f32)]),
+                )
+            })
+        }
+    }
+
+    /// A cache whose clock reads the returned time.
+    fn cache_with_clock(provider: Arc<CountingProvider>) -> (ExchangeRateCache, Arc<AtomicU64>) {
+        let now = Arc::new(AtomicU64::new(0));
+        let clock = now.clone();
+        let cache =
+            ExchangeRateCache::with_clock(provider, Arc::new(move || clock.load(Ordering::SeqCst)));
+        (cache, now)
+    }
+
+    /// Fetch at `now`, waiting for any refresh the fetch starts.
+    async fn fetch_at(
+        cache: &ExchangeRateCache,
+        clock: &AtomicU64,
+        base_currency: &str,
+        now: u64,
+    ) -> ExchangeRateTable {
+        clock.store(now, Ordering::SeqCst);
+        let (table, refresh) = cache.fetch_and_refresh(base_currency).await.unwrap();
+        if let Some(refresh) = refresh {
+            refresh.await.unwrap();
+        }
+        table
+    }
+
+    #[tokio::test]
+    async fn test_cache_serves_until_next_update() {
+        let provider = Arc::new(CountingProvider::new(usize::MAX));
+        let (cache, clock) = cache_with_clock(provider.clone());
+
+        let first = fetch_at(&cache, &clock, "NZD", 0).await;
+        let second = fetch_at(&cache, &clock, "NZD", 500).await;
+        assert_eq!(first, second);
+        assert_eq!(provider.fetches.load(Ordering::SeqCst), 1);
+
+        // Keyed by base currency.
+        fetch_at(&cache, &clock, "AUD", 500).await;
+        assert_eq!(provider.fetches.load(Ordering::SeqCst), 2);
+
+        // Past the next update the cached rates are served while refreshing in the background.
+        let expired = fetch_at(&cache, &clock, "NZD", 1000).await;
+        assert_eq!(expired.rate("AUD"), Ok(1.0));
+        assert_eq!(provider.fetches.load(Ordering::SeqCst), 3);
+        let refreshed = fetch_at(&cache, &clock, "NZD", 1001).await;
+        assert_eq!(refreshed.rate("AUD"), Ok(3.0));
+        assert!(!refreshed.stale);
+    }
+
+    #[tokio::test]
+    async fn test_cache_serves_stale_rates_when_refresh_fails() {
+        let provider = Arc::new(CountingProvider::new(1));
+        let (cache, clock) = cache_with_clock(provider.clone());
+
+        fetch_at(&cache, &clock, "NZD", 0).await;
+        let expired = fetch_at(&cache, &clock, "NZD", 2000).await;
+        assert!(!expired.stale);
+        assert_eq!(provider.fetches.load(Ordering::SeqCst), 2);
+
+        let stale = fetch_at(&cache, &clock, "NZD", 2001).await;
+        assert!(stale.stale);
+        assert_eq!(stale.rate("AUD"), Ok(1.0));
+        // Failed refreshes are not retried on every request.
+        assert_eq!(provider.fetches.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_cache_backs_off_from_failed_refreshes() {
+        let provider = Arc::new(CountingProvider::new(1));
+        let (cache, clock) = cache_with_clock(provider.clone());
+        let fetches = || provider.fetches.load(Ordering::SeqCst);
+
+        fetch_at(&cache, &clock, "NZD", 0).await;
+        // The refresh triggered at 1000 fails at 1030, so the retry waits 60s from then.
+        clock.store(1000, Ordering::SeqCst);
+        let (_, refresh) = cache.fetch_and_refresh("NZD").await.unwrap();
+        clock.store(1030, Ordering::SeqCst);
+        refresh.unwrap().await.unwrap();
+        assert_eq!(fetches(), 2);
+        fetch_at(&cache, &clock, "NZD", 1089).await;
+        assert_eq!(fetches(), 2);
+        fetch_at(&cache, &clock, "NZD", 1090).await;
+        assert_eq!(fetches(), 3);
+        // Each further failure doubles the wait.
+        fetch_at(&cache, &clock, "NZD", 1209).await;
+        assert_eq!(fetches(), 3);
+        fetch_at(&cache, &clock, "NZD", 1210).await;
+        assert_eq!(fetches(), 4);
+        fetch_at(&cache, &clock, "NZD", 1449).await;
+        assert_eq!(fetches(), 4);
+        fetch_at(&cache, &clock, "NZD", 1450).await;
+        assert_eq!(fetches(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_cache_retries_before_end_of_life() {
+        let provider = Arc::new(CountingProvider {
+            time_eol_unix: Some(1500),
+            ..CountingProvider::new(1)
+        });
+        let (cache, clock) = cache_with_clock(provider.clone());
+        let fetches = || provider.fetches.load(Ordering::SeqCst);
+
+        fetch_at(&cache, &clock, "NZD", 0).await;
+        for now in [1000, 1060, 1180, 1420] {
+            fetch_at(&cache, &clock, "NZD", now).await;
+        }
+        assert_eq!(fetches(), 5);
+        // The fourth wait of 480s would pass the end of life at 1500, so it is cut short.
+        fetch_at(&cache, &clock, "NZD", 1499).await;
+        assert_eq!(fetches(), 5);
+        let stale = fetch_at(&cache, &clock, "NZD", 1500).await;
+        assert!(stale.stale);
+        assert_eq!(fetches(), 6);
+        // Past the end of life the backoff carries on.
+        fetch_at(&cache, &clock, "NZD", 1500 + 959).await;
+        assert_eq!(fetches(), 6);
+        fetch_at(&cache, &clock, "NZD", 1500 + 960).await;
+        assert_eq!(fetches(), 7);
+    }
+
+    #[tokio::test]
+    async fn test_cache_propagates_initial_failure() {
+        let provider = Arc::new(CountingProvider::new(0));
+        let (cache, _) = cache_with_clock(provider);
+        assert!(cache.fetch_exchange_rates("NZD").await.is_err());
+    }
+}
@@ -0,0 +1,125 @@
+use crate::errors::ExchangeRateError;
+use crate::exchange_rates::{ExchangeRateProvider, ExchangeRateTable};
+use async_trait::async_trait;
+use std::sync::Arc;
+
+/// Serves any base currency from a single reference table (e.g. USD based),
+/// so supporting another normalizing currency costs no extra upstream fetches.
+/// Only when the inner provider has no reference table at all is its own table for the
+/// requested base used instead. A base missing from the reference table is unknown,
+/// and is not fetched upstream.
+pub struct CrossRateProvider {
+    provider: Arc<dyn ExchangeRateProvider>,
+    reference_currency: String,
+}
+
+impl CrossRateProvider {
+    pub fn new(provider: Arc<dyn ExchangeRateProvider>, reference_currency: &str) -> Self {
+        Self {
+            provider,
+            reference_currency: reference_currency.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl ExchangeRateProvider for CrossRateProvider {
+    async fn fetch_exchange_rates(
+        &self,
+        base_currency: &str,
+    ) -> Result<ExchangeRateTable, ExchangeRateError> {
+        match self
+            .provider
+            .fetch_exchange_rates(&self.reference_currency)
+            .await
+        {
+            Ok(reference) => reference.rebase(base_currency),
+            Err(ExchangeRateError::UnsupportedBaseCurrency(_)) => {
+                self.provider.fetch_exchange_rates(base_currency).await
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::ExchangeRateError;
+    use crate::exchange_rates::cross_rates::CrossRateProvider;
+    use crate::exchange_rates::static_provider::StaticExchangeRateProvider;
+    use crate::exchange_rates::{ExchangeRateProvider, ExchangeRateTable};
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_cross_rates_from_reference_table() {
+        let provider = CrossRateProvider::new(
+            Arc::new(
+                StaticExchangeRateProvider::from_file("test_data/exchange_rates.json").unwrap(),
+            ),
+            "NZD",
+        );
+        let aud = provider.fetch_exchange_rates("AUD").await.unwrap();
+        assert_eq!(aud.base_code, "AUD");
+        assert_eq!(aud.rate("AUD"), Ok(1.0));
+        assert_eq!(aud.rate("NZD"), Ok((1.0 / 0.9f32 as f64) as f32));
+        assert_eq!(
+            provider.fetch_exchange_rates("USD").await,
+            Err(ExchangeRateError::UnknownCurrency("USD".to_string()))
+        );
+    }
+
+    /// Counts the tables fetched from the wrapped provider.
+    struct CountingProvider {
+        provider: StaticExchangeRateProvider,
+        fetches: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl ExchangeRateProvider for CountingProvider {
+        async fn fetch_exchange_rates(
+            &self,
+            base_currency: &str,
+        ) -> Result<ExchangeRateTable, ExchangeRateError> {
+            self.fetches.fetch_add(1, Ordering::SeqCst);
+            self.provider.fetch_exchange_rates(base_currency).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cross_rates_unknown_base_is_not_fetched() {
+        let counting = Arc::new(CountingProvider {
+            provider: StaticExchangeRateProvider::from_file("test_data/exchange_rates.json")
+                .unwrap(),
+            fetches: AtomicUsize::new(0),
+        });
+        let provider = CrossRateProvider::new(counting.clone(), "NZD");
+        assert_eq!(
+            provider.fetch_exchange_rates("XXX").await,
+            Err(ExchangeRateError::UnknownCurrency("XXX".to_string()))
+        );
+        // Only the reference table was fetched.
+        assert_eq!(counting.fetches.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_cross_rates_fall_back_to_native_tables() {
+        // A rates file without the reference currency still serves its own bases.
+        let provider = CrossRateProvider::new(
+            Arc::new(
+                StaticExchangeRateProvider::from_file("test_data/exchange_rates.json").unwrap(),
+            ),
+            "USD",
+        );
+        let nzd = provider.fetch_exchange_rates("NZD").await.unwrap();
+        assert_eq!(nzd.base_code, "NZD");
+        assert_eq!(nzd.rate("AUD"), Ok(0.9));
+        assert_eq!(
+            provider.fetch_exchange_rates("AUD").await,
+            Err(ExchangeRateError::UnsupportedBaseCurrency(
+                "AUD".to_string()
+            ))
+        );
+    }
+}
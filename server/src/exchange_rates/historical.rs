@@ -0,0 +1,215 @@
+use crate::errors::ExchangeRateError;
+use crate::exchange_rates::ExchangeRateTable;
+use chrono::NaiveDate;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// Local store of past exchange rates, indexed by date.
+/// Each date holds one table; other base currencies are derived by cross rates.
+#[derive(Clone, Debug, Default)]
+pub struct HistoricalExchangeRates {
+    tables: BTreeMap<NaiveDate, ExchangeRateTable>,
+}
+
+/// One row of a CSV historical rates file.
+#[derive(Deserialize)]
+struct HistoricalExchangeRateRow {
+    date: NaiveDate,
+    base_code: String,
+    currency: String,
+    rate: f32,
+}
+
+impl HistoricalExchangeRates {
+    pub fn new(tables: BTreeMap<NaiveDate, ExchangeRateTable>) -> Self {
+        Self { tables }
+    }
+
+    /// Seed the store from a CSV file with a `date,base_code,currency,rate` header.
+    /// Dates are formatted as `YYYY-MM-DD`, and all rows of a date must share a base currency.
+    pub fn from_csv_file(path: &str) -> Result<Self, ExchangeRateError> {
+        let mut reader = csv::Reader::from_path(path)
+            .map_err(|err| ExchangeRateError::Io(format!("Reading {}: {}", path, err)))?;
+        let mut tables: BTreeMap<NaiveDate, ExchangeRateTable> = BTreeMap::new();
+        for row in reader.deserialize() {
+            let row: HistoricalExchangeRateRow =
+                row.map_err(|err| ExchangeRateError::Parse(format!("Parsing {}: {}", path, err)))?;
+            let table = tables
+                .entry(row.date)
+                .or_insert_with(|| ExchangeRateTable::new(&row.base_code, HashMap::new()));
+            if table.base_code != row.base_code {
+                return Err(ExchangeRateError::Parse(format!(
+                    "Parsing {}: rates on {} have more than one base currency ({} and {})",
+                    path, row.date, table.base_code, row.base_code
+                )));
+            }
+            table.rates.insert(row.currency, row.rate);
+        }
+        Ok(Self::new(tables))
+    }
+
+    pub fn insert(&mut self, date: NaiveDate, table: ExchangeRateTable) {
+        self.tables.insert(date, table);
+    }
+
+    /// Rates for `base_currency` as of `date`.
+    /// Falls back to the nearest earlier date when there are no rates on `date` itself,
+    /// and returns the date whose rates were used. All rates come from that one date, so a
+    /// currency missing from its table is missing from the result too, even if an older
+    /// table has it.
+    pub fn rates_on(
+        &self,
+        date: NaiveDate,
+        base_currency: &str,
+    ) -> Result<(NaiveDate, ExchangeRateTable), ExchangeRateError> {
+        let (rates_date, table) = self
+            .tables
+            .range(..=date)
+            .next_back()
+            .ok_or(ExchangeRateError::NoHistoricalRates(date))?;
+        Ok((*rates_date, table.rebase(base_currency)?))
+    }
+
+    /// Most recent date with rates.
+    pub fn latest_date(&self) -> Option<NaiveDate> {
+        self.tables.keys().next_back().copied()
+    }
+
+    /// Standard deviation of the change in the log of `currency` against `base_currency` over
+    /// `horizon_days`, estimated from the rates dated within `window_days` up to and including
+    /// `end`. Changes between consecutive rates are taken to have no drift and a variance
+    /// proportional to the days between them, so gaps such as weekends are allowed.
+    pub fn log_rate_volatility(
+        &self,
+        end: NaiveDate,
+        window_days: u32,
+        horizon_days: u32,
+        base_currency: &str,
+        currency: &str,
+    ) -> Result<f32, ExchangeRateError> {
+        let start = end - chrono::Duration::days(window_days as i64);
+        let log_rates = self
+            .tables
+            .range(start..=end)
+            .map(|(date, table)| {
+                table
+                    .rebase(base_currency)?
+                    .rate(currency)
+                    .map(|rate| (*date, (rate as f64).ln()))
+            })
+            .collect::<Result<Vec<(NaiveDate, f64)>, ExchangeRateError>>()?;
+        if log_rates.len() < 2 {
+            return Err(ExchangeRateError::NotEnoughHistoricalRates {
+                start,
+                end,
+                found: log_rates.len(),
+            });
+        }
+        let (squared_changes, days) =
+            log_rates
+                .windows(2)
+                .fold((0.0, 0.0), |(squared_changes, days), pair| {
+                    let ((date0, log_rate0), (date1, log_rate1)) = (pair[0], pair[1]);
+                    (
+                        squared_changes + (log_rate1 - log_rate0).powi(2),
+                        days + (date1 - date0).num_days() as f64,
+                    )
+                });
+        let daily_variance = squared_changes / days;
+        Ok((daily_variance * horizon_days as f64).sqrt() as f32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::ExchangeRateError;
+    use crate::exchange_rates::historical::HistoricalExchangeRates;
+    use crate::exchange_rates::ExchangeRateTable;
+    use chrono::NaiveDate;
+    use std::collections::HashMap;
+
+    fn date(date: &str) -> NaiveDate {
+        date.parse().unwrap()
+    }
+
+    #[test]
+    fn test_rates_on_date() {
+        let rates =
+            HistoricalExchangeRates::from_csv_file("test_data/historical_exchange_rates.csv")
+                .unwrap();
+
+        let (used, table) = rates.rates_on(date("2024-01-01"), "NZD").unwrap();
+        assert_eq!(used, date("2024-01-01"));
+        assert_eq!(table.rate("AUD"), Ok(0.5));
+
+        // Rates for another base are derived from the table for that date.
+        let (_, table) = rates.rates_on(date("2024-07-01"), "AUD").unwrap();
+        assert_eq!(table.rate("NZD"), Ok(4.0));
+    }
+
+    #[test]
+    fn test_rates_fall_back_to_earlier_date() {
+        let rates =
+            HistoricalExchangeRates::from_csv_file("test_data/historical_exchange_rates.csv")
+                .unwrap();
+
+        let (used, table) = rates.rates_on(date("2024-03-15"), "NZD").unwrap();
+        assert_eq!(used, date("2024-01-01"));
+        assert_eq!(table.rate("AUD"), Ok(0.5));
+
+        let (used, _) = rates.rates_on(date("2030-01-01"), "NZD").unwrap();
+        assert_eq!(used, date("2024-07-01"));
+
+        assert_eq!(
+            rates.rates_on(date("2023-12-31"), "NZD"),
+            Err(ExchangeRateError::NoHistoricalRates(date("2023-12-31")))
+        );
+    }
+
+    #[test]
+    fn test_rates_do_not_mix_dates() {
+        let mut rates =
+            HistoricalExchangeRates::from_csv_file("test_data/historical_exchange_rates.csv")
+                .unwrap();
+        rates.insert(
+            date("2024-03-01"),
+            ExchangeRateTable::new("NZD", HashMap::from([("NZD".to_string(), 1.0)])),
+        );
+
+        // AUD is only in the older table, but the nearest date's table is used as is.
+        let (used, table) = rates.rates_on(date("2024-03-15"), "NZD").unwrap();
+        assert_eq!(used, date("2024-03-01"));
+        assert_eq!(
+            table.rate("AUD"),
+            Err(ExchangeRateError::UnknownCurrency("AUD".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_log_rate_volatility() {
+        let rates =
+            HistoricalExchangeRates::from_csv_file("test_data/historical_exchange_rates.csv")
+                .unwrap();
+        assert_eq!(rates.latest_date(), Some(date("2024-07-01")));
+
+        // The log rate falls by ln(2) over the 182 days between the two dates, so the daily
+        // variance is ln(2)^2 / 182, and over 182 days the volatility is ln(2) itself.
+        let volatility = |horizon_days| {
+            rates
+                .log_rate_volatility(date("2024-07-01"), 365, horizon_days, "NZD", "AUD")
+                .unwrap()
+        };
+        assert!((volatility(182) - 2f32.ln()).abs() < 1e-6);
+        assert!((volatility(364) - 2f32.ln() * 2f32.sqrt()).abs() < 1e-6);
+        assert_eq!(volatility(0), 0.0);
+
+        assert_eq!(
+            rates.log_rate_volatility(date("2024-07-01"), 30, 365, "NZD", "AUD"),
+            Err(ExchangeRateError::NotEnoughHistoricalRates {
+                start: date("2024-06-01"),
+                end: date("2024-07-01"),
+                found: 1
+            })
+        );
+    }
+}
@@ -0,0 +1,90 @@
+use crate::errors::ExchangeRateError;
+use crate::exchange_rates::{ExchangeRateProvider, ExchangeRateTable};
+use async_trait::async_trait;
+use reqwest;
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_EXCHANGE_RATES_URL: &str = "https://open.er-api.com/v6/latest";
+
+/// Get exchange rates from an open.er-api.com compatible endpoint.
+/// Rates for a base currency are fetched from `{base_url}/{base_currency}`.
+pub struct HttpExchangeRateProvider {
+    base_url: String,
+    client: reqwest::Client,
+}
+
+impl HttpExchangeRateProvider {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+}
+
+#[async_trait]
+impl ExchangeRateProvider for HttpExchangeRateProvider {
+    async fn fetch_exchange_rates(
+        &self,
+        base_currency: &str,
+    ) -> Result<ExchangeRateTable, ExchangeRateError> {
+        let endpoint = format!("{}/{}", self.base_url, base_currency);
+        let resp = self
+            .client
+            .get(&endpoint)
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status())
+            .map_err(|err| ExchangeRateError::Request(err.to_string()))?
+            .text()
+            .await
+            .map_err(|err| ExchangeRateError::Request(err.to_string()))?;
+        let rates: ExchangeRatesResponse =
+            serde_json::from_str(&resp).map_err(|err| ExchangeRateError::Parse(err.to_string()))?;
+        if rates.result != "success" {
+            return Err(ExchangeRateError::Request(format!(
+                "{} returned result {:?}",
+                endpoint, rates.result
+            )));
+        }
+        Ok(ExchangeRateTable {
+            time_next_update_unix: Some(rates.time_next_update_unix as u64),
+            // The provider uses 0 when no end of life is scheduled.
+            time_eol_unix: match rates.time_eol_unix {
+                0 => None,
+                eol => Some(eol as u64),
+            },
+            ..ExchangeRateTable::new(&rates.base_code, rates.rates)
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExchangeRatesResponse {
+    result: String,
+    provider: String,
+    documentation: String,
+    terms_of_use: String,
+    time_last_update_unix: u32,
+    time_last_update_utc: String,
+    time_next_update_unix: u32,
+    time_next_update_utc: String,
+    time_eol_unix: u32,
+    base_code: String,
+    pub rates: std::collections::HashMap<String, f32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::exchange_rates::http_provider::HttpExchangeRateProvider;
+
+    #[test]
+    fn test_base_url_is_normalised() {
+        let provider = HttpExchangeRateProvider::new("http://localhost:8080/v6/latest/");
+        assert_eq!(provider.base_url(), "http://localhost:8080/v6/latest");
+    }
+}
@@ -0,0 +1,176 @@
+pub mod cache;
+pub mod cross_rates;
+pub mod historical;
+pub mod http_provider;
+pub mod ppp;
+pub mod static_provider;
+
+use crate::errors::ExchangeRateError;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Exchange rates relative to a single base currency.
+/// `rates[currency]` is the number of units of `currency` per unit of `base_code`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ExchangeRateTable {
+    pub base_code: String,
+    pub rates: HashMap<String, f32>,
+    /// Unix time after which the provider expects to publish new rates.
+    #[serde(default)]
+    pub time_next_update_unix: Option<u64>,
+    /// Unix time after which the provider stops serving these rates.
+    #[serde(default)]
+    pub time_eol_unix: Option<u64>,
+    /// Set when the rates are past their next update time and a refresh failed.
+    #[serde(default)]
+    pub stale: bool,
+}
+
+impl ExchangeRateTable {
+    pub fn new(base_code: &str, rates: HashMap<String, f32>) -> Self {
+        Self {
+            base_code: base_code.to_string(),
+            rates,
+            time_next_update_unix: None,
+            time_eol_unix: None,
+            stale: false,
+        }
+    }
+
+    /// Units of `currency` per unit of the base currency.
+    pub fn rate(&self, currency: &str) -> Result<f32, ExchangeRateError> {
+        self.rates
+            .get(currency)
+            .copied()
+            .ok_or_else(|| ExchangeRateError::UnknownCurrency(currency.to_string()))
+    }
+
+    /// Derive the table for another base currency by cross-rate division:
+    /// `rate(new_base -> c) = rate(base -> c) / rate(base -> new_base)`.
+    /// Divisions are done in f64 so each cross rate is the correctly rounded f32 quotient,
+    /// i.e. within `f32::EPSILON / 2` relative of the exact one and exact when the quotient
+    /// is representable, and the new base maps to exactly 1.
+    /// Rebasing an already rebased table rounds once more on each of its two rates, so it
+    /// stays within `2 * f32::EPSILON` relative of rebasing the original table directly.
+    pub fn rebase(&self, base_currency: &str) -> Result<ExchangeRateTable, ExchangeRateError> {
+        if base_currency == self.base_code {
+            return Ok(self.clone());
+        }
+        let new_base_rate = self.rate(base_currency)? as f64;
+        if !(new_base_rate.is_finite() && new_base_rate > 0.0) {
+            return Err(ExchangeRateError::UnknownCurrency(
+                base_currency.to_string(),
+            ));
+        }
+        let mut rates: HashMap<String, f32> = self
+            .rates
+            .iter()
+            .map(|(currency, &rate)| (currency.clone(), (rate as f64 / new_base_rate) as f32))
+            .collect();
+        rates.insert(self.base_code.clone(), (1.0 / new_base_rate) as f32);
+        rates.insert(base_currency.to_string(), 1.0);
+        Ok(ExchangeRateTable {
+            rates,
+            base_code: base_currency.to_string(),
+            ..self.clone()
+        })
+    }
+}
+
+/// A source of exchange rates, chosen at startup.
+/// Implementations must be shareable across actix workers.
+#[async_trait]
+pub trait ExchangeRateProvider: Send + Sync {
+    /// Get the latest exchange rates with `base_currency` as the base.
+    async fn fetch_exchange_rates(
+        &self,
+        base_currency: &str,
+    ) -> Result<ExchangeRateTable, ExchangeRateError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::ExchangeRateError;
+    use crate::exchange_rates::ExchangeRateTable;
+    use std::collections::HashMap;
+
+    fn usd_table() -> ExchangeRateTable {
+        ExchangeRateTable::new(
+            "USD",
+            HashMap::from([
+                ("USD".to_string(), 1.0),
+                ("NZD".to_string(), 1.6),
+                ("AUD".to_string(), 1.5),
+                ("GBP".to_string(), 0.8),
+            ]),
+        )
+    }
+
+    #[test]
+    fn test_rebase_cross_rates() {
+        let usd = usd_table();
+        let nzd = usd.rebase("NZD").unwrap();
+        assert_eq!(nzd.base_code, "NZD");
+        assert_eq!(nzd.rate("NZD"), Ok(1.0));
+        // Each cross rate is the correctly rounded quotient of the two reference rates.
+        assert_eq!(nzd.rate("AUD"), Ok((1.5f32 as f64 / 1.6f32 as f64) as f32));
+        assert_eq!(nzd.rate("GBP"), Ok((0.8f32 as f64 / 1.6f32 as f64) as f32));
+        assert_eq!(nzd.rate("USD"), Ok((1.0 / 1.6f32 as f64) as f32));
+
+        // Triangulating through another base rounds again, within the documented bound.
+        // Exact triangulation needs representable quotients, see below.
+        let currencies = ["USD", "NZD", "AUD", "GBP"];
+        for via in currencies {
+            let intermediate = usd.rebase(via).unwrap();
+            for base in currencies {
+                let direct = usd.rebase(base).unwrap();
+                let triangulated = intermediate.rebase(base).unwrap();
+                for currency in currencies {
+                    let direct = direct.rate(currency).unwrap() as f64;
+                    let triangulated = triangulated.rate(currency).unwrap() as f64;
+                    assert!(
+                        (triangulated / direct - 1.0).abs() <= 2.0 * f32::EPSILON as f64,
+                        "{} via {}: {}",
+                        base,
+                        via,
+                        currency
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_rebase_is_exact_for_representable_rates() {
+        let usd = ExchangeRateTable::new(
+            "USD",
+            HashMap::from([("EUR".to_string(), 0.5), ("NOK".to_string(), 8.0)]),
+        );
+        let eur = usd.rebase("EUR").unwrap();
+        assert_eq!(
+            eur.rates,
+            HashMap::from([
+                ("EUR".to_string(), 1.0),
+                ("NOK".to_string(), 16.0),
+                ("USD".to_string(), 2.0),
+            ])
+        );
+        assert_eq!(eur.rebase("USD").unwrap().rate("NOK"), Ok(8.0));
+        assert_eq!(usd.rebase("USD"), Ok(usd.clone()));
+
+        // Triangulating through any base gives exactly the direct cross rates.
+        let nok = usd.rebase("NOK").unwrap();
+        let nok_from_eur = eur.rebase("NOK").unwrap();
+        assert_eq!(nok.rates, nok_from_eur.rates);
+        assert_eq!(nok.rate("EUR"), Ok(0.0625));
+    }
+
+    #[test]
+    fn test_rebase_unknown_currency() {
+        assert_eq!(
+            usd_table().rebase("XXX"),
+            Err(ExchangeRateError::UnknownCurrency("XXX".to_string()))
+        );
+    }
+}
@@ -0,0 +1,81 @@
+use crate::errors::ExchangeRateError;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+
+/// Purchasing power parity conversion factors, keyed by country.
+/// `factors[country]` is the number of units of the country's currency that buy
+/// as much as one unit of `base_code` does (e.g. LCU per international dollar).
+/// They are keyed by country rather than currency because price levels differ
+/// between countries sharing a currency.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct PppConversionFactors {
+    pub base_code: String,
+    pub factors: HashMap<String, f32>,
+}
+
+impl Default for PppConversionFactors {
+    fn default() -> Self {
+        Self {
+            base_code: "USD".to_string(),
+            factors: HashMap::new(),
+        }
+    }
+}
+
+impl PppConversionFactors {
+    /// Read factors from a JSON file, all of which must be finite and positive.
+    pub fn from_json_file(path: &str) -> Result<Self, ExchangeRateError> {
+        let file = fs::File::open(path)
+            .map_err(|err| ExchangeRateError::Io(format!("Reading {}: {}", path, err)))?;
+        let ppp: Self = serde_json::from_reader(file)
+            .map_err(|err| ExchangeRateError::Parse(format!("Parsing {}: {}", path, err)))?;
+        if let Some((country, factor)) = ppp
+            .factors
+            .iter()
+            .find(|(_, factor)| !(factor.is_finite() && **factor > 0.0))
+        {
+            return Err(ExchangeRateError::Parse(format!(
+                "Parsing {}: factor for {} must be positive, got {}",
+                path, country, factor
+            )));
+        }
+        Ok(ppp)
+    }
+
+    pub fn factor(&self, country: &str) -> Result<f32, ExchangeRateError> {
+        self.factors
+            .get(country)
+            .copied()
+            .ok_or_else(|| ExchangeRateError::NoPppConversionFactor(country.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::ExchangeRateError;
+    use crate::exchange_rates::ppp::PppConversionFactors;
+
+    #[test]
+    fn test_ppp_conversion_factors_from_file() {
+        let ppp =
+            PppConversionFactors::from_json_file("test_data/ppp_conversion_factors.json").unwrap();
+        assert_eq!(ppp.base_code, "USD");
+        assert_eq!(ppp.factor("New Zealand"), Ok(1.5));
+        assert_eq!(
+            ppp.factor("Foo"),
+            Err(ExchangeRateError::NoPppConversionFactor("Foo".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_ppp_conversion_factors_must_be_positive() {
+        assert_eq!(
+            PppConversionFactors::from_json_file("test_data/invalid_ppp_conversion_factors.json"),
+            Err(ExchangeRateError::Parse(
+                "Parsing test_data/invalid_ppp_conversion_factors.json: factor for Atlantis must be positive, got 0"
+                    .to_string()
+            ))
+        );
+    }
+}
@@ -0,0 +1,142 @@
+use crate::errors::ExchangeRateError;
+use crate::exchange_rates::{ExchangeRateProvider, ExchangeRateTable};
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Exchange rates held in memory, keyed by base currency.
+/// Used as a fixture in tests, and for serving rates from a local file.
+#[derive(Clone, Debug)]
+pub struct StaticExchangeRateProvider {
+    tables: HashMap<String, ExchangeRateTable>,
+}
+
+/// A JSON rates file holds either one table (e.g. a saved open.er-api.com response) or many.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ExchangeRatesFile {
+    Single(ExchangeRateTable),
+    Many(Vec<ExchangeRateTable>),
+}
+
+/// One row of a CSV rates file.
+#[derive(Deserialize)]
+struct ExchangeRateRow {
+    base_code: String,
+    currency: String,
+    rate: f32,
+}
+
+impl StaticExchangeRateProvider {
+    pub fn new(tables: Vec<ExchangeRateTable>) -> Self {
+        Self {
+            tables: tables
+                .into_iter()
+                .map(|table| (table.base_code.clone(), table))
+                .collect(),
+        }
+    }
+
+    /// Load rates from a `.json` or `.csv` file.
+    /// CSV files need a `base_code,currency,rate` header.
+    pub fn from_file(path: &str) -> Result<Self, ExchangeRateError> {
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("json") => Self::from_json_file(path),
+            Some("csv") => Self::from_csv_file(path),
+            _ => Err(ExchangeRateError::Io(format!(
+                "Unsupported exchange rates file {}, expected .json or .csv",
+                path
+            ))),
+        }
+    }
+
+    fn from_json_file(path: &str) -> Result<Self, ExchangeRateError> {
+        let file = fs::File::open(path)
+            .map_err(|err| ExchangeRateError::Io(format!("Reading {}: {}", path, err)))?;
+        let tables = match serde_json::from_reader(file)
+            .map_err(|err| ExchangeRateError::Parse(format!("Parsing {}: {}", path, err)))?
+        {
+            ExchangeRatesFile::Single(table) => vec![table],
+            ExchangeRatesFile::Many(tables) => tables,
+        };
+        Ok(Self::new(tables))
+    }
+
+    fn from_csv_file(path: &str) -> Result<Self, ExchangeRateError> {
+        let mut reader = csv::Reader::from_path(path)
+            .map_err(|err| ExchangeRateError::Io(format!("Reading {}: {}", path, err)))?;
+        let mut tables: HashMap<String, ExchangeRateTable> = HashMap::new();
+        for row in reader.deserialize() {
+            let row: ExchangeRateRow =
+                row.map_err(|err| ExchangeRateError::Parse(format!("Parsing {}: {}", path, err)))?;
+            tables
+                .entry(row.base_code.clone())
+                .or_insert_with(|| ExchangeRateTable::new(&row.base_code, HashMap::new()))
+                .rates
+                .insert(row.currency, row.rate);
+        }
+        Ok(Self::new(tables.into_values().collect()))
+    }
+}
+
+#[async_trait]
+impl ExchangeRateProvider for StaticExchangeRateProvider {
+    async fn fetch_exchange_rates(
+        &self,
+        base_currency: &str,
+    ) -> Result<ExchangeRateTable, ExchangeRateError> {
+        self.tables
+            .get(base_currency)
+            .cloned()
+            .ok_or_else(|| ExchangeRateError::UnsupportedBaseCurrency(base_currency.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::ExchangeRateError;
+    use crate::exchange_rates::static_provider::StaticExchangeRateProvider;
+    use crate::exchange_rates::ExchangeRateProvider;
+
+    #[tokio::test]
+    async fn test_rates_from_json_file() {
+        let provider =
+            StaticExchangeRateProvider::from_file("test_data/exchange_rates.json").unwrap();
+        let table = provider.fetch_exchange_rates("NZD").await.unwrap();
+        assert_eq!(table.base_code, "NZD");
+        assert_eq!(table.rate("AUD"), Ok(0.9));
+        assert_eq!(
+            table.rate("XXX"),
+            Err(ExchangeRateError::UnknownCurrency("XXX".to_string()))
+        );
+        assert_eq!(
+            provider.fetch_exchange_rates("USD").await,
+            Err(ExchangeRateError::UnsupportedBaseCurrency(
+                "USD".to_string()
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rates_from_csv_file() {
+        let provider =
+            StaticExchangeRateProvider::from_file("test_data/exchange_rates.csv").unwrap();
+        let nzd = provider.fetch_exchange_rates("NZD").await.unwrap();
+        assert_eq!(nzd.rate("NZD"), Ok(1.0));
+        assert_eq!(nzd.rate("AUD"), Ok(0.9));
+        let aud = provider.fetch_exchange_rates("AUD").await.unwrap();
+        assert_eq!(aud.rate("NZD"), Ok(1.1));
+    }
+
+    #[test]
+    fn test_unsupported_rates_file() {
+        assert!(StaticExchangeRateProvider::from_file("test_data/foo.txt").is_err());
+        assert!(StaticExchangeRateProvider::from_file("test_data/missing.json").is_err());
+    }
+}
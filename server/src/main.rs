@@ -1,21 +1,134 @@
 use actix_cors::Cors;
 use actix_web::{web, App, HttpServer};
 use std::env;
+use std::process;
+use std::sync::Arc;
 
-use taxes_compare::controller::handle_request::handle_request;
+use taxes_compare::controller::handle_batch::{handle_batch, MAX_BATCH_BYTES};
+use taxes_compare::controller::handle_countries::{handle_countries, handle_country};
+use taxes_compare::controller::handle_gross_income::handle_gross_income;
+use taxes_compare::controller::handle_request::{handle_request, json_error_handler};
+use taxes_compare::controller::handle_salary_equivalence::handle_salary_equivalence;
+use taxes_compare::controller::handle_tax::handle_tax;
+use taxes_compare::controller::handle_tie_exchange_rate::handle_tie_exchange_rate;
+use taxes_compare::controller::openapi::handle_openapi;
 use taxes_compare::controller::taxes_config::TaxesConfig;
+use taxes_compare::exchange_rates::cache::ExchangeRateCache;
+use taxes_compare::exchange_rates::cross_rates::CrossRateProvider;
+use taxes_compare::exchange_rates::historical::HistoricalExchangeRates;
+use taxes_compare::exchange_rates::http_provider::{
+    HttpExchangeRateProvider, DEFAULT_EXCHANGE_RATES_URL,
+};
+use taxes_compare::exchange_rates::ppp::PppConversionFactors;
+use taxes_compare::exchange_rates::static_provider::StaticExchangeRateProvider;
+use taxes_compare::exchange_rates::ExchangeRateProvider;
+
+/// Pick the exchange rate backend from the environment.
+/// `EXCHANGE_RATE_PROVIDER=file` serves rates from `EXCHANGE_RATES_PATH` (.json or .csv),
+/// otherwise rates are fetched over HTTP from `EXCHANGE_RATES_URL`.
+fn exchange_rate_provider() -> Arc<dyn ExchangeRateProvider> {
+    match env::var("EXCHANGE_RATE_PROVIDER").as_deref() {
+        Ok("file") => {
+            let Ok(path) = env::var("EXCHANGE_RATES_PATH") else {
+                eprintln!("EXCHANGE_RATES_PATH must be set when EXCHANGE_RATE_PROVIDER=file");
+                process::exit(1);
+            };
+            match StaticExchangeRateProvider::from_file(&path) {
+                Ok(provider) => Arc::new(provider),
+                Err(err) => {
+                    eprintln!("Could not load exchange rates: {}", err);
+                    process::exit(1);
+                }
+            }
+        }
+        Ok("http") | Err(_) => Arc::new(HttpExchangeRateProvider::new(
+            &env::var("EXCHANGE_RATES_URL")
+                .unwrap_or_else(|_| String::from(DEFAULT_EXCHANGE_RATES_URL)),
+        )),
+        Ok(other) => {
+            eprintln!(
+                "Unknown EXCHANGE_RATE_PROVIDER {}, expected http or file",
+                other
+            );
+            process::exit(1);
+        }
+    }
+}
+
+/// Load historical exchange rates from `HISTORICAL_EXCHANGE_RATES_PATH` if it is set.
+fn historical_exchange_rates() -> HistoricalExchangeRates {
+    match env::var("HISTORICAL_EXCHANGE_RATES_PATH") {
+        Ok(path) => HistoricalExchangeRates::from_csv_file(&path).unwrap_or_else(|err| {
+            eprintln!("Could not load historical exchange rates: {}", err);
+            process::exit(1);
+        }),
+        Err(_) => HistoricalExchangeRates::default(),
+    }
+}
+
+/// Load PPP conversion factors from `PPP_CONVERSION_FACTORS_PATH` if it is set.
+fn ppp_conversion_factors() -> PppConversionFactors {
+    match env::var("PPP_CONVERSION_FACTORS_PATH") {
+        Ok(path) => PppConversionFactors::from_json_file(&path).unwrap_or_else(|err| {
+            eprintln!("Could not load PPP conversion factors: {}", err);
+            process::exit(1);
+        }),
+        Err(_) => PppConversionFactors::default(),
+    }
+}
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     env_logger::init();
-    let taxes_config = TaxesConfig::new(
+    let taxes_config = match TaxesConfig::load(
         &env::var("TAXES_CONFIG_PATH").unwrap_or_else(|_| String::from("./assets/taxes.json")),
-    );
+    ) {
+        Ok(taxes_config) => taxes_config,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    };
+    // Only the reference table is fetched and cached, other bases are derived from it.
+    let exchange_rate_provider: Arc<dyn ExchangeRateProvider> = Arc::new(CrossRateProvider::new(
+        Arc::new(ExchangeRateCache::new(exchange_rate_provider())),
+        &env::var("EXCHANGE_RATES_REFERENCE_CURRENCY").unwrap_or_else(|_| String::from("USD")),
+    ));
+    let exchange_rate_provider = web::Data::from(exchange_rate_provider);
+    let historical_exchange_rates = web::Data::new(historical_exchange_rates());
+    let ppp_conversion_factors = web::Data::new(ppp_conversion_factors());
     HttpServer::new(move || {
         App::new()
             .wrap(Cors::permissive())
+            .app_data(web::JsonConfig::default().error_handler(json_error_handler))
             .app_data(web::Data::new(taxes_config.clone()))
+            .app_data(exchange_rate_provider.clone())
+            .app_data(historical_exchange_rates.clone())
+            .app_data(ppp_conversion_factors.clone())
+            .service(
+                web::scope("/v1")
+                    .route("/process", web::post().to(handle_request))
+                    .route("/tax", web::post().to(handle_tax))
+                    .route("/gross_income", web::post().to(handle_gross_income))
+                    .route(
+                        "/salary_equivalence",
+                        web::post().to(handle_salary_equivalence),
+                    )
+                    .service(
+                        web::resource("/batch")
+                            .app_data(web::PayloadConfig::new(MAX_BATCH_BYTES))
+                            .route(web::post().to(handle_batch)),
+                    )
+                    .route("/countries", web::get().to(handle_countries))
+                    .route("/countries/{id}", web::get().to(handle_country))
+                    .route(
+                        "/tie_exchange_rate",
+                        web::post().to(handle_tie_exchange_rate),
+                    ),
+            )
+            // Unversioned alias kept for existing clients.
             .route("/process", web::post().to(handle_request))
+            .route("/openapi.json", web::get().to(handle_openapi))
     })
     .bind(format!(
         "0.0.0.0:{}",
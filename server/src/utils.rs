@@ -2,31 +2,33 @@ use crate::controller::taxes_config;
 use crate::core::points::tax_amount::{IncomeTaxKnot, IncomeTaxPoint};
 use crate::core::schedules::amount_schedule::IncomeTaxAmountSchedule;
 use crate::core::segment::LinearPiecewiseSegment;
+use crate::errors::TaxError;
 use rayon::prelude::*;
 
-/// Utility function for generating a range of income points.
-pub fn generate_range(start: f32, stop: f32, step: f32) -> Vec<f32> {
-    let mut values = Vec::new();
-    let mut current = start;
-    while current <= stop {
-        values.push(current);
-        current += step;
-    }
-    values
-}
-
 /// Util for adjusting schedule by exchange rate
 pub fn adjust_exchange_rate_schedule(
     tax_config: &taxes_config::TaxesConfig,
     country: &str,
     exchange_rate: &Option<f32>,
     max_income_to_consider: f32,
-) -> IncomeTaxAmountSchedule {
-    tax_config
-        .get_country(&country)
-        .unwrap()
-        .exchange_rate_adjustment(&exchange_rate)
-        .to_income_amount_schedule(max_income_to_consider)
+) -> Result<IncomeTaxAmountSchedule, TaxError> {
+    Ok(tax_config
+        .get_country(country)
+        .ok_or_else(|| TaxError::UnknownCountry(country.to_string()))?
+        .exchange_rate_adjustment(exchange_rate)
+        .to_income_amount_schedule(max_income_to_consider))
+}
+
+/// Breakeven points of two schedules, leaving out the origin since it is not interesting.
+pub fn compute_breakevens_excluding_origin(
+    schedule_one: &IncomeTaxAmountSchedule,
+    schedule_two: &IncomeTaxAmountSchedule,
+) -> Vec<IncomeTaxPoint> {
+    schedule_one
+        .compute_breakeven_taxes(schedule_two)
+        .into_iter()
+        .filter(|point| !(point.income() == 0.0 && point.income_tax_amount() == 0.0))
+        .collect()
 }
 
 /// Util for testing that points are approx eq.
@@ -111,6 +113,7 @@ mod tests {
     use crate::core::points::tax_amount::IncomeTaxKnot;
     use crate::core::schedules::amount_schedule::IncomeTaxAmountSchedule;
     use crate::core::segment::LinearPiecewiseSegment;
+    use crate::errors::TaxError;
     use crate::utils::adjust_exchange_rate_schedule;
     use crate::utils::group_incomes_by_segment;
 
@@ -120,19 +123,18 @@ mod tests {
         let country = "Foo";
         let max_income_to_consider = 390000.0;
         let schedule_one =
-            adjust_exchange_rate_schedule(&tax_config, &country, &None, max_income_to_consider);
-        let schedule_two = adjust_exchange_rate_schedule(
-            &tax_config,
-            &country,
-            &Some(2.0),
-            max_income_to_consider,
-        );
+            adjust_exchange_rate_schedule(&tax_config, country, &None, max_income_to_consider)
+                .unwrap();
+        let schedule_two =
+            adjust_exchange_rate_schedule(&tax_config, country, &Some(2.0), max_income_to_consider)
+                .unwrap();
         let schedule_three = adjust_exchange_rate_schedule(
             &tax_config,
-            &country,
+            country,
             &Some(1.0 / 2.0),
             max_income_to_consider,
-        );
+        )
+        .unwrap();
 
         assert_eq!(
             schedule_one,
@@ -165,19 +167,18 @@ mod tests {
 
         let max_income_to_consider = 400000.0;
         let schedule_one =
-            adjust_exchange_rate_schedule(&tax_config, &country, &None, max_income_to_consider);
-        let schedule_two = adjust_exchange_rate_schedule(
-            &tax_config,
-            &country,
-            &Some(2.0),
-            max_income_to_consider,
-        );
+            adjust_exchange_rate_schedule(&tax_config, country, &None, max_income_to_consider)
+                .unwrap();
+        let schedule_two =
+            adjust_exchange_rate_schedule(&tax_config, country, &Some(2.0), max_income_to_consider)
+                .unwrap();
         let schedule_three = adjust_exchange_rate_schedule(
             &tax_config,
-            &country,
+            country,
             &Some(1.0 / 2.0),
             max_income_to_consider,
-        );
+        )
+        .unwrap();
 
         assert_eq!(
             schedule_one,
@@ -209,6 +210,15 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_adjust_exchange_rate_schedule_unknown_country() {
+        let tax_config = taxes_config::TaxesConfig::new("test_data/foo.json");
+        assert_eq!(
+            adjust_exchange_rate_schedule(&tax_config, "Bar", &None, 390000.0),
+            Err(TaxError::UnknownCountry("Bar".to_string()))
+        );
+    }
+
     #[test]
     fn test_group_incomes_by_segment() {
         let incomes = vec![500.0, 1500.0, 1700.0, 2500.0, 3500.0];